@@ -0,0 +1,93 @@
+//! Compares keeping the `SpatialIndex` between frames against rebuilding it every frame, on a
+//! level with tens of thousands of static tiles and a few hundred movers.
+//!
+//! Run with `cargo bench --bench spatial_index`.
+
+use std::time::{Duration, Instant};
+
+use bevy_collision_2d::{
+    prelude::*,
+    system::{update_spatial_index, SpatialIndex},
+};
+use bevy_ecs::{prelude::*, system::ScheduleSystem};
+use bevy_math::Vec2;
+
+const TILE_SIZE: f32 = 16.;
+const LEVEL_SIZE: i32 = 200;
+const MOVERS: i32 = 300;
+const FRAMES: u32 = 100;
+
+#[derive(Component)]
+struct Mover;
+
+fn spawn_level(world: &mut World) {
+    for x in 0..LEVEL_SIZE {
+        for y in 0..LEVEL_SIZE {
+            let position = Vec2::new(x as f32, y as f32) * TILE_SIZE;
+            world.spawn((
+                Static,
+                KinematicBody::aabb(Vec2::splat(TILE_SIZE), position, Vec2::ZERO),
+            ));
+        }
+    }
+    for i in 0..MOVERS {
+        let position = Vec2::splat(i as f32 * TILE_SIZE * 0.5);
+        world.spawn((
+            Mover,
+            KinematicBody::aabb(Vec2::splat(TILE_SIZE), position, Vec2::X),
+        ));
+    }
+}
+
+fn move_bodies(mut query: Query<&mut KinematicBody, With<Mover>>) {
+    for mut body in query.iter_mut() {
+        body.position += Vec2::new(TILE_SIZE * 0.25, 0.);
+    }
+}
+
+/// What `detect_collisions` used to do: register every body again each frame
+fn rebuild_spatial_index(mut index: ResMut<SpatialIndex>, query: Query<(Entity, &KinematicBody)>) {
+    *index = SpatialIndex::new(TILE_SIZE * 4.);
+    for (entity, body) in query.iter() {
+        index.insert(entity, body);
+    }
+}
+
+fn query_candidates(index: Res<SpatialIndex>, query: Query<&KinematicBody, With<Mover>>) {
+    for body in query.iter() {
+        let (min, max) = body.swept_bounds();
+        std::hint::black_box(index.candidates(min, max));
+    }
+}
+
+fn run<M>(name: &str, update: impl IntoScheduleConfigs<ScheduleSystem, M>) -> Duration {
+    let mut world = World::new();
+    world.insert_resource(SpatialIndex::new(TILE_SIZE * 4.));
+    spawn_level(&mut world);
+
+    let mut schedule = Schedule::default();
+    schedule.add_systems((move_bodies, update, query_candidates).chain());
+    // The first frame registers the whole level either way
+    schedule.run(&mut world);
+
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        schedule.run(&mut world);
+    }
+    let elapsed = start.elapsed() / FRAMES;
+    println!(
+        "{name:>12}: {elapsed:>10.2?} per frame ({} bodies)",
+        world.resource::<SpatialIndex>().len()
+    );
+    elapsed
+}
+
+fn main() {
+    let rebuilt = run("rebuilt", rebuild_spatial_index);
+    let persistent = run("persistent", update_spatial_index);
+    println!(
+        "{:>12}: {:.1}x",
+        "speedup",
+        rebuilt.as_secs_f64() / persistent.as_secs_f64()
+    );
+}
@@ -48,16 +48,19 @@ fn main() {
         chunk_size: TILE_SIZE,
         enable_debug: true,
         enable_collision_effects: true,
+        report_all_contacts: true,
+        ..Default::default()
     })
     .add_systems(Startup, setup)
     .add_systems(Update, spawn_projectile)
     .add_systems(Update, movement.in_set(Kinematics::Motion))
+    .add_systems(Update, track_velocity.after(Kinematics::Collision))
     .run();
 }
 
 #[derive(Component)]
 pub struct Projectile {
-    pub direction: Vec2,
+    velocity: Vec2,
 }
 
 #[derive(Component)]
@@ -78,6 +81,7 @@ pub fn setup(mut commands: Commands) {
             Wall,
             Transform::from_xyz(position.x, position.y, -1.),
             KinematicBody::aabb(size, position, Vec2::ZERO),
+            Static,
         ));
     }
 }
@@ -98,16 +102,33 @@ pub fn spawn_projectile(mut commands: Commands) {
                 ..Default::default()
             },
             Transform::from_xyz(position.x, position.y, 0.),
-            Projectile { direction },
+            Projectile {
+                velocity: direction * TILE_SIZE * PROJECTILE_SPEED,
+            },
             KinematicBody::aabb(size, position, Vec2::ZERO),
+            CollisionResponse::Bounce { restitution: 1. },
         ))
         .observe(listen_collision_effects);
 }
 
 pub fn movement(time: Res<Time>, mut query: Query<(&mut KinematicBody, &Projectile)>) {
     let t = time.delta_secs();
-    for (mut k, p) in &mut query {
-        k.motion = p.direction * TILE_SIZE * PROJECTILE_SPEED * t;
+    for (mut k, projectile) in &mut query {
+        k.motion = projectile.velocity * t;
+    }
+}
+
+pub fn track_velocity(time: Res<Time>, mut query: Query<(&KinematicBody, &mut Projectile)>) {
+    let t = time.delta_secs();
+    if t == 0. {
+        return;
+    }
+    // Bounced motion is kept for the next frame, already reflected and scaled by restitution.
+    // Projectiles spawned since the collision pass have not moved yet.
+    for (k, mut projectile) in &mut query {
+        if k.motion != Vec2::ZERO {
+            projectile.velocity = k.motion / t;
+        }
     }
 }
 
@@ -115,20 +136,15 @@ pub fn listen_collision_effects(
     trigger: Trigger<CollisionEffect>,
     mut commands: Commands,
     mut walls: Query<&mut Sprite, (With<Wall>, Without<Projectile>)>,
-    mut projectiles: Query<(&mut Projectile, &mut Sprite), Without<Wall>>,
+    mut projectiles: Query<&mut Sprite, (With<Projectile>, Without<Wall>)>,
 ) {
     let target = trigger.target();
-    if let Ok((mut projectile, mut sprite)) = projectiles.get_mut(target) {
+    if let Ok(mut sprite) = projectiles.get_mut(target) {
         if let Ok(mut wall_sprite) = walls.get_mut(trigger.other) {
             wall_sprite.color = Color::WHITE;
         }
-        if let Some(normal) = trigger.collision.normal {
-            // Projectile should rebound in the axis of the collision normal.
-            if normal.x != 0 {
-                projectile.direction.x *= -1.;
-            } else {
-                projectile.direction.y *= -1.;
-            }
+        // The plugin rebounds the projectile itself.
+        if trigger.collision.normal.is_some() {
             // First collision should change the projectile to be black.
             // Second collision should despawn it.
             if sprite.color == Color::WHITE {
@@ -70,6 +70,7 @@ pub fn setup(mut commands: Commands) {
         Transform::default(),
         Player,
         KinematicBody::aabb(Vec2::splat(TILE_SIZE), position, Vec2::ZERO),
+        CollisionResponse::Slide,
     ));
 
     for (x, y) in WALL_POSITIONS {
@@ -82,6 +83,7 @@ pub fn setup(mut commands: Commands) {
             },
             Transform::from_xyz(position.x, position.y, -1.),
             KinematicBody::aabb(Vec2::splat(TILE_SIZE), position, Vec2::ZERO),
+            Static,
         ));
     }
 }
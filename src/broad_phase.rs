@@ -0,0 +1,534 @@
+use bevy_ecs::entity::Entity;
+use bevy_math::Vec2;
+use bevy_platform::collections::HashMap;
+
+use crate::{
+    kinematics::{KinematicBody, Shape},
+    utils::{
+        aabb_tree::{Aabb, AabbTree, ProxyId},
+        chunk_map::{ChunkId, ChunkMap},
+    },
+};
+
+/// Finds the bodies that may touch a box, so narrow phase tests only run against those
+pub trait BroadPhase: Send + Sync {
+    /// Registers `entity` to be found from anywhere in `min..max`, moving it if it was already
+    /// registered. Backends may use the body's shape to register it more tightly.
+    fn insert(&mut self, entity: Entity, body: &KinematicBody, min: Vec2, max: Vec2);
+
+    /// Unregisters `entity`, returning whether it was registered
+    fn remove(&mut self, entity: Entity) -> bool;
+
+    fn contains(&self, entity: Entity) -> bool;
+
+    /// Calls `f` on every entity that may overlap `min..max`. An entity may be visited more
+    /// than once.
+    fn query(&self, min: Vec2, max: Vec2, f: &mut dyn FnMut(Entity));
+
+    /// Catches up on the inserts and removals since the last call, so that queries are fast
+    /// again. Called after each batch of changes; backends that stay current do nothing.
+    fn update(&mut self) {}
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The available `BroadPhase` backends
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BroadPhaseKind {
+    /// A uniform grid of `chunk_size` chunks, best when bodies are all about one chunk large
+    #[default]
+    ChunkGrid,
+    /// Boxes kept sorted along the x axis, best for few bodies spread out horizontally
+    SortAndSweep,
+    /// A bounding volume hierarchy, independent of how large bodies are. Boxes are fattened by
+    /// `margin` so bodies moving less than that are not reinserted.
+    AabbTree { margin: f32 },
+}
+
+impl BroadPhaseKind {
+    pub fn build(self, chunk_size: f32) -> Box<dyn BroadPhase> {
+        match self {
+            BroadPhaseKind::ChunkGrid => Box::new(ChunkGrid::new(chunk_size)),
+            BroadPhaseKind::SortAndSweep => Box::new(SortAndSweep::default()),
+            BroadPhaseKind::AabbTree { margin } => Box::new(AabbTreeBroadPhase::new(margin)),
+        }
+    }
+}
+
+/// The most chunks a body is registered in, or a query scans, before the grid falls back to
+/// checking boxes directly
+pub const MAX_GRID_CHUNKS: u64 = 64;
+
+/// Registers bodies in every chunk of a `ChunkMap` they cover. Bodies covering more than
+/// `MAX_GRID_CHUNKS` chunks are kept in a list of large bodies that every query checks instead.
+#[derive(Clone)]
+pub struct ChunkGrid {
+    chunks: ChunkMap<Entity>,
+    entries: HashMap<Entity, Vec<ChunkId>>,
+    large: HashMap<Entity, Aabb>,
+}
+
+impl ChunkGrid {
+    pub fn new(chunk_size: f32) -> Self {
+        Self {
+            chunks: ChunkMap::new(0, chunk_size),
+            entries: HashMap::new(),
+            large: HashMap::new(),
+        }
+    }
+
+    /// How many chunks the box spanning `min..max` covers, or the segment from `min` to `max`
+    /// crosses
+    fn chunk_count(&self, min: Vec2, max: Vec2, segment: bool) -> u64 {
+        let (min, max) = (self.chunks.chunk_id(min), self.chunks.chunk_id(max));
+        let width = (max.x as i64 - min.x as i64).unsigned_abs();
+        let height = (max.y as i64 - min.y as i64).unsigned_abs();
+        if segment {
+            width + height + 1
+        } else {
+            (width + 1).saturating_mul(height + 1)
+        }
+    }
+}
+
+impl BroadPhase for ChunkGrid {
+    fn insert(&mut self, entity: Entity, body: &KinematicBody, min: Vec2, max: Vec2) {
+        // Long diagonal segments only cover the chunks along them, not their whole box
+        let segment = match body.shape {
+            Shape::Segment(start, end) => Some((body.position + start, body.position + end)),
+            _ => None,
+        };
+        let count = match segment {
+            Some((start, end)) => self.chunk_count(start, end, true),
+            None => self.chunk_count(min, max, false),
+        };
+        if count > MAX_GRID_CHUNKS {
+            self.remove(entity);
+            self.large.insert(entity, Aabb::new(min, max));
+            return;
+        }
+
+        let ids = match segment {
+            Some((start, end)) => self.chunks.segment_chunk_ids(start, end),
+            None => self.chunks.chunk_ids(min, max).collect(),
+        };
+        if self.entries.get(&entity) == Some(&ids) {
+            return;
+        }
+
+        self.remove(entity);
+        for id in ids.iter().copied() {
+            self.chunks.map.entry(id).or_default().push(entity);
+        }
+        self.entries.insert(entity, ids);
+    }
+
+    fn remove(&mut self, entity: Entity) -> bool {
+        if self.large.remove(&entity).is_some() {
+            return true;
+        }
+        let Some(ids) = self.entries.remove(&entity) else {
+            return false;
+        };
+        for id in ids {
+            self.chunks.remove(id, &entity);
+        }
+        true
+    }
+
+    fn contains(&self, entity: Entity) -> bool {
+        self.entries.contains_key(&entity) || self.large.contains_key(&entity)
+    }
+
+    fn query(&self, min: Vec2, max: Vec2, f: &mut dyn FnMut(Entity)) {
+        let aabb = Aabb::new(min, max);
+        for (entity, _) in self.large.iter().filter(|(_, other)| other.overlaps(&aabb)) {
+            f(*entity);
+        }
+
+        // Scanning the occupied chunks is cheaper than looking up every chunk of a huge box
+        if self.chunk_count(min, max, false) > MAX_GRID_CHUNKS {
+            let (min, max) = (self.chunks.chunk_id(min), self.chunks.chunk_id(max));
+            let covered =
+                |id: &ChunkId| (min.x..=max.x).contains(&id.x) && (min.y..=max.y).contains(&id.y);
+            for (_, entities) in self.chunks.map.iter().filter(|(id, _)| covered(id)) {
+                for entity in entities {
+                    f(*entity);
+                }
+            }
+            return;
+        }
+        self.chunks.iter_bounds(min, max, |_id, entity| f(*entity));
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len() + self.large.len()
+    }
+}
+
+/// Keeps boxes sorted by their left edge, so a query only scans the boxes starting between its
+/// own left edge, less the widest box, and its right edge. Boxes are changed in place and the
+/// order is restored by `update`, which is cheap since bodies barely move between frames.
+#[derive(Clone, Default)]
+pub struct SortAndSweep {
+    sorted: Vec<(Aabb, Entity)>,
+    /// Where each entity's box is in `sorted`
+    entries: HashMap<Entity, usize>,
+    /// The widest box as of the last `update`
+    max_width: f32,
+    /// Whether boxes changed since the last `update`, in which case queries scan every box
+    dirty: bool,
+}
+
+impl SortAndSweep {
+    fn start(&self, x: f32) -> usize {
+        self.sorted.partition_point(|(aabb, _)| aabb.min.x < x)
+    }
+}
+
+impl BroadPhase for SortAndSweep {
+    fn insert(&mut self, entity: Entity, _body: &KinematicBody, min: Vec2, max: Vec2) {
+        let aabb = Aabb::new(min, max);
+        match self.entries.get(&entity) {
+            Some(&index) if self.sorted[index].0 == aabb => return,
+            Some(&index) => self.sorted[index].0 = aabb,
+            None => {
+                self.entries.insert(entity, self.sorted.len());
+                self.sorted.push((aabb, entity));
+            }
+        }
+        self.dirty = true;
+    }
+
+    fn remove(&mut self, entity: Entity) -> bool {
+        let Some(index) = self.entries.remove(&entity) else {
+            return false;
+        };
+        self.sorted.swap_remove(index);
+        if let Some((_, moved)) = self.sorted.get(index) {
+            self.entries.insert(*moved, index);
+        }
+        self.dirty = true;
+        true
+    }
+
+    fn contains(&self, entity: Entity) -> bool {
+        self.entries.contains_key(&entity)
+    }
+
+    fn query(&self, min: Vec2, max: Vec2, f: &mut dyn FnMut(Entity)) {
+        let aabb = Aabb::new(min, max);
+        let start = if self.dirty {
+            0
+        } else {
+            self.start(min.x - self.max_width)
+        };
+        for (other, entity) in &self.sorted[start..] {
+            if !self.dirty && other.min.x > max.x {
+                break;
+            }
+            if other.overlaps(&aabb) {
+                f(*entity);
+            }
+        }
+    }
+
+    fn update(&mut self) {
+        if !self.dirty {
+            return;
+        }
+
+        // Insertion sort, which only moves the few boxes that passed a neighbor
+        for i in 1..self.sorted.len() {
+            let mut j = i;
+            while j > 0 && self.sorted[j - 1].0.min.x > self.sorted[j].0.min.x {
+                self.sorted.swap(j - 1, j);
+                self.entries.insert(self.sorted[j].1, j);
+                j -= 1;
+            }
+            if j != i {
+                self.entries.insert(self.sorted[j].1, j);
+            }
+        }
+        self.max_width = self
+            .sorted
+            .iter()
+            .map(|(aabb, _)| aabb.max.x - aabb.min.x)
+            .fold(0., f32::max);
+        self.dirty = false;
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// Stores bodies in an `AabbTree`, predicting that each keeps moving by its current motion
+#[derive(Clone, Default)]
+pub struct AabbTreeBroadPhase {
+    tree: AabbTree<Entity>,
+    entries: HashMap<Entity, ProxyId>,
+}
+
+impl AabbTreeBroadPhase {
+    pub fn new(margin: f32) -> Self {
+        Self {
+            tree: AabbTree::new(margin),
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn tree(&self) -> &AabbTree<Entity> {
+        &self.tree
+    }
+}
+
+impl BroadPhase for AabbTreeBroadPhase {
+    fn insert(&mut self, entity: Entity, body: &KinematicBody, min: Vec2, max: Vec2) {
+        let aabb = Aabb::new(min, max);
+        match self.entries.get(&entity) {
+            Some(&id) => {
+                self.tree.move_proxy(id, aabb, body.motion);
+            }
+            None => {
+                let id = self.tree.insert(aabb, entity);
+                self.entries.insert(entity, id);
+            }
+        }
+    }
+
+    fn remove(&mut self, entity: Entity) -> bool {
+        let Some(id) = self.entries.remove(&entity) else {
+            return false;
+        };
+        self.tree.remove(id);
+        true
+    }
+
+    fn contains(&self, entity: Entity) -> bool {
+        self.entries.contains_key(&entity)
+    }
+
+    fn query(&self, min: Vec2, max: Vec2, f: &mut dyn FnMut(Entity)) {
+        self.tree
+            .query(Aabb::new(min, max), |_id, entity| f(*entity));
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [BroadPhaseKind; 3] = [
+        BroadPhaseKind::ChunkGrid,
+        BroadPhaseKind::SortAndSweep,
+        BroadPhaseKind::AabbTree { margin: 1. },
+    ];
+
+    fn query(broad_phase: &dyn BroadPhase, min: Vec2, max: Vec2) -> Vec<u32> {
+        let mut actual = Vec::new();
+        broad_phase.query(min, max, &mut |entity| actual.push(entity.index()));
+        actual.sort();
+        actual.dedup();
+        actual
+    }
+
+    fn insert(broad_phase: &mut dyn BroadPhase, index: u32, body: KinematicBody) {
+        let (min, max) = body.swept_bounds();
+        broad_phase.insert(Entity::from_raw(index), &body, min, max);
+    }
+
+    #[test]
+    fn test_broad_phase_query() {
+        for kind in KINDS {
+            let mut broad_phase = kind.build(10.);
+            insert(
+                &mut *broad_phase,
+                0,
+                KinematicBody::aabb(Vec2::splat(4.), Vec2::new(5., 5.), Vec2::ZERO),
+            );
+            insert(
+                &mut *broad_phase,
+                1,
+                KinematicBody::aabb(Vec2::splat(100.), Vec2::new(200., 0.), Vec2::ZERO),
+            );
+            insert(
+                &mut *broad_phase,
+                2,
+                KinematicBody::point(Vec2::new(-50., 5.), Vec2::new(100., 0.)),
+            );
+            insert(
+                &mut *broad_phase,
+                3,
+                KinematicBody::point(Vec2::new(500., 500.), Vec2::ZERO),
+            );
+            assert_eq!(broad_phase.len(), 4, "{kind:?}");
+            broad_phase.update();
+
+            assert_eq!(
+                query(&*broad_phase, Vec2::new(4., 4.), Vec2::new(6., 6.)),
+                vec![0, 2],
+                "{kind:?}"
+            );
+            assert_eq!(
+                query(&*broad_phase, Vec2::new(140., -10.), Vec2::new(160., 10.)),
+                vec![1],
+                "{kind:?}"
+            );
+            assert_eq!(
+                query(&*broad_phase, Vec2::new(300., 300.), Vec2::new(400., 400.)),
+                Vec::<u32>::new(),
+                "{kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_broad_phase_move_and_remove() {
+        for kind in KINDS {
+            let mut broad_phase = kind.build(10.);
+            for i in 0..5 {
+                insert(
+                    &mut *broad_phase,
+                    i,
+                    KinematicBody::point(Vec2::new(i as f32 * 20., 0.), Vec2::ZERO),
+                );
+            }
+            insert(
+                &mut *broad_phase,
+                4,
+                KinematicBody::point(Vec2::new(5., 5.), Vec2::ZERO),
+            );
+            assert!(broad_phase.remove(Entity::from_raw(0)), "{kind:?}");
+            assert!(!broad_phase.remove(Entity::from_raw(0)), "{kind:?}");
+            assert!(!broad_phase.contains(Entity::from_raw(0)), "{kind:?}");
+            assert_eq!(broad_phase.len(), 4, "{kind:?}");
+            broad_phase.update();
+
+            assert_eq!(
+                query(&*broad_phase, Vec2::new(0., 0.), Vec2::new(9., 9.)),
+                vec![4],
+                "{kind:?}"
+            );
+            assert_eq!(
+                query(&*broad_phase, Vec2::new(75., -5.), Vec2::new(85., 5.)),
+                Vec::<u32>::new(),
+                "{kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_chunk_grid_large_bodies() {
+        let mut broad_phase = ChunkGrid::new(1.);
+        insert(
+            &mut broad_phase,
+            0,
+            KinematicBody::aabb(Vec2::splat(2000.), Vec2::ZERO, Vec2::ZERO),
+        );
+        insert(
+            &mut broad_phase,
+            1,
+            KinematicBody::point(Vec2::new(-500., 0.), Vec2::new(5000., 0.)),
+        );
+        insert(
+            &mut broad_phase,
+            2,
+            KinematicBody::point(Vec2::new(3000., 3000.), Vec2::ZERO),
+        );
+        assert_eq!(broad_phase.large.len(), 2);
+        assert!(broad_phase.chunks.map.len() < 10);
+
+        assert_eq!(
+            query(&broad_phase, Vec2::new(900., 900.), Vec2::new(901., 901.)),
+            vec![0]
+        );
+        assert_eq!(
+            query(&broad_phase, Vec2::new(4000., -1.), Vec2::new(4001., 1.)),
+            vec![1]
+        );
+        // Huge queries scan the occupied chunks instead of every chunk they cover
+        assert_eq!(
+            query(&broad_phase, Vec2::splat(1500.), Vec2::splat(1e9)),
+            vec![2]
+        );
+
+        // Shrinking a large body moves it back into the chunks
+        insert(
+            &mut broad_phase,
+            0,
+            KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::ZERO),
+        );
+        assert_eq!(broad_phase.large.len(), 1);
+        assert_eq!(broad_phase.len(), 3);
+        assert_eq!(
+            query(&broad_phase, Vec2::new(900., 900.), Vec2::new(901., 901.)),
+            Vec::<u32>::new()
+        );
+        assert!(broad_phase.remove(Entity::from_raw(1)));
+        assert!(!broad_phase.contains(Entity::from_raw(1)));
+    }
+
+    #[test]
+    fn test_sort_and_sweep_update() {
+        let mut broad_phase = SortAndSweep::default();
+        insert(
+            &mut broad_phase,
+            0,
+            KinematicBody::aabb(Vec2::splat(100.), Vec2::ZERO, Vec2::ZERO),
+        );
+        for i in 1..4 {
+            insert(
+                &mut broad_phase,
+                i,
+                KinematicBody::point(Vec2::new(i as f32 * 20., 0.), Vec2::ZERO),
+            );
+        }
+
+        // Queries are correct before the boxes are sorted
+        assert_eq!(
+            query(&broad_phase, Vec2::new(35., -5.), Vec2::new(45., 5.)),
+            vec![0, 2]
+        );
+        broad_phase.update();
+        assert_eq!(broad_phase.max_width, 100.);
+        assert_eq!(
+            query(&broad_phase, Vec2::new(35., -5.), Vec2::new(45., 5.)),
+            vec![0, 2]
+        );
+
+        // Narrowing the wide box and moving a point past its neighbors
+        insert(
+            &mut broad_phase,
+            0,
+            KinematicBody::aabb(Vec2::splat(1.), Vec2::ZERO, Vec2::ZERO),
+        );
+        insert(
+            &mut broad_phase,
+            1,
+            KinematicBody::point(Vec2::new(70., 0.), Vec2::ZERO),
+        );
+        assert!(broad_phase.remove(Entity::from_raw(3)));
+        broad_phase.update();
+        assert_eq!(broad_phase.max_width, 1.);
+        assert_eq!(
+            query(&broad_phase, Vec2::new(35., -5.), Vec2::new(75., 5.)),
+            vec![1, 2]
+        );
+        assert_eq!(
+            query(&broad_phase, Vec2::new(-5., -5.), Vec2::new(5., 5.)),
+            vec![0]
+        );
+        for (index, (_, entity)) in broad_phase.sorted.iter().enumerate() {
+            assert_eq!(broad_phase.entries[entity], index);
+        }
+    }
+}
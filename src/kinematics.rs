@@ -1,5 +1,8 @@
-use bevy_ecs::component::Component;
-use bevy_math::{IVec2, Vec2};
+use std::ops::Deref;
+
+use bevy_ecs::{component::Component, entity::Entity};
+use bevy_math::{IVec2, Rot2, Vec2};
+use bevy_platform::collections::HashSet;
 use bitflags::bitflags;
 
 bitflags! {
@@ -14,270 +17,2588 @@ bitflags! {
         const G = 0b0100_0000;
         const H = 0b1000_0000;
     }
-}
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+/// How `mask` and `layer` flags decide whether two bodies may collide
+pub enum FilterMode {
+    /// A body collides with another if its mask shares a flag with the other's layer
+    #[default]
+    Asymmetric,
+    /// Both bodies' masks must share a flag with the other's layer
+    Symmetric,
+}
+
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq)]
+/// How a body's motion is resolved once it hits something
+pub enum CollisionResponse {
+    /// Stop at the point of contact
+    #[default]
+    Stop,
+    /// Project the remaining motion onto the contact surface and keep moving
+    Slide,
+    /// Reflect the remaining motion about the contact normal, scaling the rebound by
+    /// `restitution`. Bouncing bodies keep their reflected motion for the next frame.
+    Bounce { restitution: f32 },
+    /// Move through whatever is hit, only reporting the contacts
+    PassThrough,
+}
+
+impl CollisionResponse {
+    /// The motion to re-test after a hit, given the motion left over at the time of impact
+    pub fn resolve(&self, remaining: Vec2, normal: Option<Vec2>) -> Option<Vec2> {
+        match (self, normal) {
+            (CollisionResponse::Slide, Some(normal)) => {
+                Some(remaining - normal * remaining.dot(normal))
+            }
+            (CollisionResponse::Bounce { restitution }, Some(normal)) => {
+                Some(remaining - (1. + restitution) * normal * remaining.dot(normal))
+            }
+            (CollisionResponse::PassThrough, _) => Some(remaining),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Component, Clone, Debug, Default)]
+/// Marks a body as a trigger volume that reports overlaps without blocking any motion
+pub struct Sensor {
+    pub(crate) overlaps: HashSet<Entity>,
+}
+
+impl Sensor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The bodies overlapping this sensor as of the last collision pass
+    pub fn overlaps(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.overlaps.iter().copied()
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug, Default)]
+/// Marks a body as level geometry that never moves. Static bodies are indexed separately, are
+/// only re-indexed when they change and are never swept as movers.
+pub struct Static;
+
+#[derive(Debug, Default, Copy, Clone)]
+/// A point of collision between two objects
+pub struct Collision {
+    /// The resolved motion of the collision
+    pub motion: Vec2,
+    /// The fraction of the body's motion at which the collision happens, negative if the
+    /// bodies already overlap
+    pub toi: f32,
+    /// The global position at which the objects collision
+    pub position: Vec2,
+    /// The normal of the collision snapped to the closest axis, for tile-style games
+    pub normal: Option<IVec2>,
+    /// The exact unit normal of the collision, for surfaces that are not axis-aligned
+    pub unit_normal: Option<Vec2>,
+}
+
+impl Collision {
+    /// The collision with its motion pulled back along the normal so that a body moving by
+    /// `body_motion` stops `skin` away from the surface, keeping its motion along the surface.
+    /// `toi` becomes how far along `body_motion` the pulled back motion reaches. Overlaps are
+    /// left as they are.
+    pub fn with_skin(mut self, body_motion: Vec2, skin: f32) -> Self {
+        let Some(normal) = self.unit_normal else {
+            return self;
+        };
+        let approach = -body_motion.dot(normal);
+        if skin <= 0. || approach <= 0. || self.toi < 0. {
+            return self;
+        }
+
+        // Never back away from the surface, only stop short of it
+        let pullback = skin.min(-self.motion.dot(normal)).max(0.);
+        self.motion += pullback * normal;
+        self.toi = self.motion.dot(body_motion) / body_motion.length_squared();
+        self
+    }
+
+    /// The same collision as seen from the struck body, with the normal flipped
+    pub fn mirrored(&self) -> Self {
+        Self {
+            normal: self.normal.map(|normal| -normal),
+            unit_normal: self.unit_normal.map(|normal| -normal),
+            ..*self
+        }
+    }
+}
+
+/// How deep two overlapping bodies are inside each other
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Penetration {
+    /// The distance the body must move along `normal` to separate
+    pub depth: f32,
+    /// The unit direction that separates the body, pointing away from the other body
+    pub normal: Vec2,
+}
+
+impl Penetration {
+    /// The minimum translation vector that separates the body from the other
+    pub fn mtv(&self) -> Vec2 {
+        self.normal * self.depth
+    }
+
+    /// The contact of an overlapping body at `position` that is kept from moving any deeper
+    pub fn contact(&self, position: Vec2) -> Collision {
+        Collision {
+            motion: Vec2::ZERO,
+            toi: 0.,
+            position,
+            normal: Some(axis_normal(self.normal)),
+            unit_normal: Some(self.normal),
+        }
+    }
+}
+
+impl PartialEq for Collision {
+    fn eq(&self, other: &Self) -> bool {
+        self.position == other.position
+            && self.normal == other.normal
+            && (self.position.x - other.position.x).abs() < 0.001
+            && (self.position.y - other.position.y).abs() < 0.001
+    }
+}
+
+const CORNERS: [[f32; 2]; 4] = [[-1., -1.], [-1., 1.], [1., 1.], [1., -1.]];
+
+/// Sweeps a point from `origin` along `motion` against the box spanning `min..max`, returning the
+/// entry time and the normal of the face that was entered
+fn sweep_aabb(origin: Vec2, motion: Vec2, min: Vec2, max: Vec2) -> Option<(f32, IVec2)> {
+    if motion == Vec2::ZERO {
+        return None;
+    }
+
+    let mut t_entry = f32::NEG_INFINITY;
+    let mut t_exit = f32::INFINITY;
+    let mut normal = IVec2::ZERO;
+    for axis in 0..2 {
+        if motion[axis] == 0.0 {
+            // A ray parallel to a slab only hits if it starts strictly between its planes
+            if origin[axis] <= min[axis] || origin[axis] >= max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let inv_displacement = 1.0 / motion[axis];
+        let t_min = (min[axis] - origin[axis]) * inv_displacement;
+        let t_max = (max[axis] - origin[axis]) * inv_displacement;
+        let t_near = t_min.min(t_max);
+        let t_far = t_min.max(t_max);
+
+        if t_near > t_entry {
+            t_entry = t_near;
+            normal = IVec2::ZERO;
+            normal[axis] = -motion[axis].signum() as i32;
+        }
+        t_exit = t_exit.min(t_far);
+    }
+
+    // If the line misses the AABB or the collision is outside the line segment, return None
+    if t_entry > t_exit || t_exit <= 0.0 || t_entry > 1.0 {
+        return None;
+    }
+
+    Some((t_entry, normal))
+}
+
+/// Sweeps a point from `origin` along `motion` against the circle around `center`, returning the
+/// entry time and the normal at the point of entry
+fn sweep_circle(origin: Vec2, motion: Vec2, center: Vec2, radius: f32) -> Option<(f32, Vec2)> {
+    let a = motion.length_squared();
+    if a == 0.0 {
+        return None;
+    }
+
+    // Solve |offset + t * motion| = radius for t
+    let offset = origin - center;
+    let b = offset.dot(motion);
+    let c = offset.length_squared() - radius * radius;
+    let discriminant = b * b - a * c;
+    // A ray grazing the circle does not hit it
+    if discriminant <= 0.0 {
+        return None;
+    }
+
+    let root = discriminant.sqrt();
+    let t_entry = (-b - root) / a;
+    let t_exit = (-b + root) / a;
+    if t_exit <= 0.0 || t_entry > 1.0 {
+        return None;
+    }
+
+    Some((t_entry, (offset + t_entry * motion).normalize_or_zero()))
+}
+
+/// Sweeps a point from `origin` along `motion` against the box spanning `min..max` rounded by
+/// `radius`, which is the Minkowski sum of the box and a circle
+fn sweep_rounded_aabb(
+    origin: Vec2,
+    motion: Vec2,
+    min: Vec2,
+    max: Vec2,
+    radius: f32,
+) -> Option<(f32, Vec2)> {
+    // The rounded box is the union of the box stretched along each axis and a circle per corner
+    let pad_x = Vec2::new(radius, 0.);
+    let pad_y = Vec2::new(0., radius);
+    let faces = [(min - pad_x, max + pad_x), (min - pad_y, max + pad_y)]
+        .into_iter()
+        .filter_map(|(min, max)| sweep_aabb(origin, motion, min, max))
+        .map(|(t, normal)| (t, normal.as_vec2()));
+    let corners = [min, Vec2::new(min.x, max.y), max, Vec2::new(max.x, min.y)]
+        .into_iter()
+        .filter_map(|corner| sweep_circle(origin, motion, corner, radius));
+    faces
+        .chain(corners)
+        .min_by(|(t_1, _), (t_2, _)| t_1.total_cmp(t_2))
+}
+
+/// Sweeps a point from `origin` along `motion` against the side of the segment from `start` to
+/// `end` facing `normal`, returning the entry time and that normal. Points moving along or away
+/// from the side are never hit, even if they already touch it.
+fn sweep_side(
+    origin: Vec2,
+    motion: Vec2,
+    start: Vec2,
+    end: Vec2,
+    normal: Vec2,
+) -> Option<(f32, Vec2)> {
+    if motion.dot(normal) >= 0.0 {
+        return None;
+    }
+
+    let edge = end - start;
+    let cross = motion.perp_dot(edge);
+    if cross == 0.0 {
+        return None; // Lines are parallel or collinear
+    }
+
+    let offset = start - origin;
+    let t_entry = offset.perp_dot(edge) / cross;
+    let edge_ratio = offset.perp_dot(motion) / cross;
+    if !(0.0..=1.0).contains(&t_entry) || !(0.0..=1.0).contains(&edge_ratio) {
+        return None;
+    }
+
+    Some((t_entry, normal))
+}
+
+/// Sweeps a point from `origin` along `motion` against the segment from `start` to `end`,
+/// returning the entry time and the segment normal facing the point. A point lying on the
+/// segment cannot tell which side it came from, so it is blocked both ways.
+fn sweep_segment(origin: Vec2, motion: Vec2, start: Vec2, end: Vec2) -> Option<(f32, Vec2)> {
+    let normal = (end - start).perp().normalize_or_zero();
+    let side = (origin - start).dot(normal);
+    if side < 0.0 || (side == 0.0 && normal.dot(motion) > 0.0) {
+        sweep_side(origin, motion, start, end, -normal)
+    } else {
+        sweep_side(origin, motion, start, end, normal)
+    }
+}
+
+/// The unit normals of the edges of the convex vertex set `vertices`, in either winding order
+fn edge_normals(vertices: &[Vec2]) -> impl Iterator<Item = Vec2> + '_ {
+    vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(start, end)| (*end - *start).perp().normalize_or_zero())
+        .filter(|normal| *normal != Vec2::ZERO)
+}
+
+/// The range covered by the projection of `vertices` onto `axis`
+fn project(vertices: &[Vec2], axis: Vec2) -> (f32, f32) {
+    vertices.iter().map(|vertex| vertex.dot(axis)).fold(
+        (f32::INFINITY, f32::NEG_INFINITY),
+        |(min, max), projection| (min.min(projection), max.max(projection)),
+    )
+}
+
+/// Whether the convex vertex sets strictly overlap on every separating axis
+fn polygons_overlap(vertices: &[Vec2], other: &[Vec2]) -> bool {
+    // Two sets of single points have no axes to separate them, and never strictly overlap
+    let mut axes = edge_normals(vertices).chain(edge_normals(other)).peekable();
+    axes.peek().is_some()
+        && axes.all(|axis| {
+            let (min, max) = project(vertices, axis);
+            let (other_min, other_max) = project(other, axis);
+            max > other_min && min < other_max
+        })
+}
+
+/// Whether the circle around `center` overlaps the convex vertex set `vertices`
+fn circle_overlaps_polygon(center: Vec2, radius: f32, vertices: &[Vec2]) -> bool {
+    polygons_overlap(&[center], vertices)
+        || vertices
+            .iter()
+            .zip(vertices.iter().cycle().skip(1))
+            .any(|(&start, &end)| {
+                let edge = end - start;
+                let ratio = ((center - start).dot(edge) / edge.length_squared().max(f32::EPSILON))
+                    .clamp(0., 1.);
+                (start + ratio * edge).distance(center) < radius
+            })
+}
+
+/// Whether the segment `core` comes within `radius` of the convex vertex set `vertices`
+fn rounded_segment_overlaps(core: [Vec2; 2], radius: f32, vertices: &[Vec2]) -> bool {
+    polygons_overlap(&core, vertices)
+        || core
+            .iter()
+            .any(|end| circle_overlaps_polygon(*end, radius, vertices))
+        || vertices
+            .iter()
+            .any(|vertex| circle_overlaps_polygon(*vertex, radius, &core))
+}
+
+/// The shallowest overlap of two convex vertex sets rounded by a radius each, with the normal
+/// pointing away from `other`
+fn rounded_penetration(
+    (vertices, radius): (&[Vec2], f32),
+    (other, other_radius): (&[Vec2], f32),
+) -> Option<Penetration> {
+    // Rounded sets can also be separated along the line between any two of their vertices
+    let vertex_axes = vertices
+        .iter()
+        .flat_map(|vertex| {
+            other
+                .iter()
+                .map(move |other_vertex| *other_vertex - *vertex)
+        })
+        .filter(|_| radius + other_radius > 0.)
+        .map(Vec2::normalize_or_zero)
+        .filter(|axis| *axis != Vec2::ZERO);
+    let axes = [Vec2::X, Vec2::Y]
+        .into_iter()
+        .chain(edge_normals(vertices))
+        .chain(edge_normals(other))
+        .chain(vertex_axes);
+
+    let mut penetration: Option<Penetration> = None;
+    for axis in axes {
+        let (min, max) = project(vertices, axis);
+        let (other_min, other_max) = project(other, axis);
+        let forward = other_max + other_radius - (min - radius);
+        let backward = max + radius - (other_min - other_radius);
+        if forward <= 0. || backward <= 0. {
+            return None;
+        }
+
+        let candidate = if forward < backward {
+            Penetration {
+                depth: forward,
+                normal: axis,
+            }
+        } else {
+            Penetration {
+                depth: backward,
+                normal: -axis,
+            }
+        };
+        if penetration.is_none_or(|penetration| candidate.depth < penetration.depth) {
+            penetration = Some(candidate);
+        }
+    }
+    penetration
+}
+
+/// Sweeps a point from `origin` along `motion` against the convex vertex set `vertices` rounded
+/// by `radius`, returning the entry time and the outward normal
+fn sweep_rounded_polygon(
+    origin: Vec2,
+    motion: Vec2,
+    vertices: &[Vec2],
+    radius: f32,
+) -> Option<(f32, Vec2)> {
+    // The rounded polygon is bounded by each edge shifted outward and by a circle per vertex.
+    // Flat sets run back along their own edges, so either winding covers both of their sides.
+    let edges = || vertices.iter().zip(vertices.iter().cycle().skip(1));
+    let area: f32 = edges().map(|(start, end)| start.perp_dot(*end)).sum();
+    let outward = if area < 0. { 1. } else { -1. };
+    let sides = edges().filter_map(|(&start, &end)| {
+        let normal = outward * (end - start).perp().normalize_or_zero();
+        let offset = normal * radius;
+        sweep_side(origin, motion, start + offset, end + offset, normal)
+    });
+    let corners = vertices
+        .iter()
+        .filter_map(|vertex| sweep_circle(origin, motion, *vertex, radius));
+    sides
+        .chain(corners)
+        .min_by(|(t_1, _), (t_2, _)| t_1.total_cmp(t_2))
+}
+
+/// Sweeps a point from `origin` along `motion` against the convex vertex set `vertices` stretched
+/// along the half segment `core` and rounded by `radius`, returning the entry time and the
+/// outward normal
+fn sweep_stretched_polygon(
+    origin: Vec2,
+    motion: Vec2,
+    vertices: &[Vec2],
+    core: Vec2,
+    radius: f32,
+) -> Option<(f32, Vec2)> {
+    if core == Vec2::ZERO {
+        return sweep_rounded_polygon(origin, motion, vertices, radius);
+    }
+
+    // The stretched polygon is bounded by the polygon at either end of the core and by the core
+    // around each vertex, which saves building its hull
+    let ends = [-core, core].map(|shift| {
+        let shifted = Vertices::derived(vertices.iter().map(|vertex| *vertex + shift));
+        sweep_rounded_polygon(origin, motion, &shifted, radius)
+    });
+    let cores = vertices.iter().map(|vertex| {
+        sweep_rounded_polygon(origin, motion, &[*vertex - core, *vertex + core], radius)
+    });
+    ends.into_iter()
+        .chain(cores)
+        .flatten()
+        .min_by(|(t_1, _), (t_2, _)| t_1.total_cmp(t_2))
+}
+
+/// Sweeps the convex vertex set `vertices` along `motion` against the convex set `other` on the
+/// separating axes of both, returning the entry time and the normal facing the moving set
+fn sweep_polygons(vertices: &[Vec2], other: &[Vec2], motion: Vec2) -> Option<(f32, Vec2)> {
+    if motion == Vec2::ZERO {
+        return None;
+    }
+
+    let mut t_entry = f32::NEG_INFINITY;
+    let mut t_exit = f32::INFINITY;
+    let mut normal = Vec2::ZERO;
+    for axis in edge_normals(vertices).chain(edge_normals(other)) {
+        let (min, max) = project(vertices, axis);
+        let (other_min, other_max) = project(other, axis);
+        let speed = motion.dot(axis);
+        if speed == 0.0 {
+            // Without motion along the axis the ranges must already overlap
+            if max <= other_min || min >= other_max {
+                return None;
+            }
+            continue;
+        }
+
+        let t_1 = (other_min - max) / speed;
+        let t_2 = (other_max - min) / speed;
+        let (t_near, t_far) = if speed > 0.0 { (t_1, t_2) } else { (t_2, t_1) };
+        if t_near > t_entry {
+            t_entry = t_near;
+            normal = -axis * speed.signum();
+        }
+        t_exit = t_exit.min(t_far);
+    }
+
+    if t_entry > t_exit || t_exit <= 0.0 || t_entry > 1.0 || normal == Vec2::ZERO {
+        return None;
+    }
+    Some((t_entry, normal))
+}
+
+/// The middle of the region where two touching convex vertex sets meet, with `normal` pointing
+/// from `other` toward `vertices`
+fn contact_point(vertices: &[Vec2], other: &[Vec2], normal: Vec2) -> Vec2 {
+    const TOLERANCE: f32 = 1e-3;
+
+    // Only the features lying on the touching faces count toward the contact
+    let tangent = normal.perp();
+    let (min, _) = project(vertices, normal);
+    let (_, other_max) = project(other, normal);
+    let feature = |vertices: &[Vec2], depth: f32| {
+        Vertices::derived(
+            vertices
+                .iter()
+                .filter(|vertex| (vertex.dot(normal) - depth).abs() < TOLERANCE)
+                .copied(),
+        )
+    };
+    let (start, end) = project(&feature(vertices, min), tangent);
+    let (other_start, other_end) = project(&feature(other, other_max), tangent);
+    let middle = 0.5 * (start.max(other_start) + end.min(other_end));
+    other_max * normal + middle * tangent
+}
+
+/// The axis-aligned direction closest to `normal`, or zero for a degenerate normal
+fn axis_normal(normal: Vec2) -> IVec2 {
+    if normal == Vec2::ZERO {
+        IVec2::ZERO
+    } else if normal.x.abs() >= normal.y.abs() {
+        IVec2::new(normal.x.signum() as i32, 0)
+    } else {
+        IVec2::new(0, normal.y.signum() as i32)
+    }
+}
+
+/// The most vertices a polygon collider may have
+pub const MAX_POLYGON_VERTICES: usize = 8;
+
+/// Up to `MAX_POLYGON_VERTICES` points stored inline, so shapes stay `Copy` and narrow phase
+/// tests never allocate
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vertices {
+    points: [Vec2; MAX_POLYGON_VERTICES],
+    len: usize,
+}
+
+impl Vertices {
+    /// The points in order, or `None` if there are more than `MAX_POLYGON_VERTICES`
+    pub fn new(points: impl IntoIterator<Item = Vec2>) -> Option<Self> {
+        let mut vertices = Self::default();
+        for point in points {
+            *vertices.points.get_mut(vertices.len)? = point;
+            vertices.len += 1;
+        }
+        Some(vertices)
+    }
+
+    /// Points taken from a shape's own vertices or a few fixed corners, which always fit
+    fn derived(points: impl IntoIterator<Item = Vec2>) -> Self {
+        Self::new(points).expect("shapes never derive more vertices than a polygon holds")
+    }
+}
+
+impl Deref for Vertices {
+    type Target = [Vec2];
+
+    fn deref(&self) -> &[Vec2] {
+        &self.points[..self.len]
+    }
+}
+
+/// The collider shape of a body
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub enum Shape {
+    /// A single point
+    #[default]
+    Point,
+    /// An axis-aligned box of the given size
+    Aabb(Vec2),
+    /// A circle of the given radius
+    Circle(f32),
+    /// A static line segment between two points relative to the body's position. Only sensor
+    /// segments are moved by their motion.
+    Segment(Vec2, Vec2),
+    /// A convex polygon with vertices relative to the body's position, in either winding order
+    Polygon(Vertices),
+    /// A capsule filling a box of the given size, rounded at the ends of its longer axis
+    Capsule(Vec2),
+    /// A box of the given size rotated counterclockwise by an angle in radians, which is synced
+    /// from the entity's `Transform` rotation
+    Obb(Vec2, f32),
+}
+
+/// A swept collision test between two bodies of specific shapes
+pub type CollisionTest = fn(&KinematicBody, &KinematicBody) -> Option<Collision>;
+
+impl Shape {
+    /// The swept test between a body of this shape and a body of `other` shape, or `None` if
+    /// the pair is not supported
+    pub fn collision_test(&self, other: &Shape) -> Option<CollisionTest> {
+        let test: CollisionTest = match (self, other) {
+            (Shape::Point, Shape::Point) => point_point,
+            (Shape::Point, Shape::Aabb(_)) => point_aabb,
+            (Shape::Aabb(_), Shape::Point) => aabb_point,
+            (Shape::Aabb(_), Shape::Aabb(_)) => aabb_aabb,
+            (Shape::Circle(_) | Shape::Point, Shape::Circle(_) | Shape::Point) => circle_circle,
+            (Shape::Circle(_), Shape::Aabb(_)) => circle_aabb,
+            (Shape::Aabb(_), Shape::Circle(_)) => aabb_circle,
+            (Shape::Point, Shape::Segment(..)) => point_segment,
+            (Shape::Aabb(_), Shape::Segment(..)) => aabb_segment,
+            (Shape::Circle(_), Shape::Segment(..)) => circle_segment,
+            (
+                Shape::Polygon(_) | Shape::Obb(..),
+                Shape::Point
+                | Shape::Aabb(_)
+                | Shape::Segment(..)
+                | Shape::Polygon(_)
+                | Shape::Obb(..),
+            )
+            | (Shape::Point | Shape::Aabb(_), Shape::Polygon(_) | Shape::Obb(..)) => {
+                polygon_polygon
+            }
+            (Shape::Capsule(_), Shape::Point | Shape::Circle(_)) => capsule_circle,
+            (Shape::Point | Shape::Circle(_), Shape::Capsule(_)) => circle_capsule,
+            (Shape::Capsule(_), Shape::Aabb(_)) => capsule_aabb,
+            (Shape::Aabb(_), Shape::Capsule(_)) => aabb_capsule,
+            (Shape::Capsule(_), Shape::Segment(..)) => capsule_segment,
+            (Shape::Capsule(_), Shape::Capsule(_)) => capsule_capsule,
+            (Shape::Circle(_) | Shape::Capsule(_), Shape::Polygon(_) | Shape::Obb(..)) => {
+                round_polygon
+            }
+            (Shape::Polygon(_) | Shape::Obb(..), Shape::Circle(_) | Shape::Capsule(_)) => {
+                polygon_round
+            }
+            // Segments are static, so they are never swept themselves
+            (Shape::Segment(..), _) => return None,
+        };
+        Some(test)
+    }
+
+    /// The half segment along the longer axis of a capsule and the radius around it
+    pub fn capsule_core(&self) -> Option<(Vec2, f32)> {
+        let Shape::Capsule(size) = *self else {
+            return None;
+        };
+        if size.y > size.x {
+            Some((Vec2::new(0., 0.5 * (size.y - size.x)), 0.5 * size.x))
+        } else {
+            Some((Vec2::new(0.5 * (size.x - size.y), 0.), 0.5 * size.y))
+        }
+    }
+
+    /// The half core and radius of a capsule, treating circles as capsules with no core
+    fn rounded_core(&self) -> Option<(Vec2, f32)> {
+        self.capsule_core()
+            .or_else(|| self.radius().map(|radius| (Vec2::ZERO, radius)))
+    }
+
+    /// The radius of a circle, treating a point as a circle of no radius
+    fn radius(&self) -> Option<f32> {
+        match self {
+            Shape::Point => Some(0.),
+            Shape::Circle(radius) => Some(*radius),
+            _ => None,
+        }
+    }
+}
+
+fn point_point(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let cross = body.motion.perp_dot(other.motion);
+    if cross == 0.0 {
+        return None; // Lines are parallel or collinear
+    }
+
+    let pos_displacement = other.position - body.position;
+
+    let self_ratio = pos_displacement.perp_dot(other.motion) / cross;
+    let other_ratio = pos_displacement.perp_dot(body.motion) / cross;
+
+    // Check if the collision point lies on both line segments
+    if (0.0..=1.0).contains(&self_ratio) && (0.0..1.0).contains(&other_ratio) {
+        // The other point's path is the surface that was hit, facing against this motion
+        let mut normal = other.motion.perp().normalize();
+        if normal.dot(body.motion) > 0.0 {
+            normal = -normal;
+        }
+        let motion = self_ratio * body.motion;
+        Some(Collision {
+            motion,
+            toi: self_ratio,
+            position: body.position + motion,
+            normal: Some(axis_normal(normal)),
+            unit_normal: Some(normal),
+        })
+    } else {
+        None // No collision within the line segments
+    }
+}
+
+fn point_aabb(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let Shape::Aabb(other_size) = other.shape else {
+        return None;
+    };
+
+    // Sweep the point against the box using the motion relative to the box, so the result
+    // matches the box's own test against the point
+    let half_size = 0.5 * other_size;
+    let (t_entry, normal) = sweep_aabb(
+        body.position,
+        body.motion - other.motion,
+        other.position - half_size,
+        other.position + half_size,
+    )?;
+
+    let motion = t_entry * body.motion;
+    Some(Collision {
+        motion,
+        toi: t_entry,
+        position: body.position + motion,
+        normal: Some(normal),
+        unit_normal: Some(normal.as_vec2()),
+    })
+}
+
+fn aabb_aabb(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let (Shape::Aabb(size), Shape::Aabb(other_size)) = (body.shape, other.shape) else {
+        return None;
+    };
+
+    // Sweep the box center along the relative motion against the Minkowski sum of both boxes,
+    // which also catches obstacles smaller than the box itself
+    let relative_motion = body.motion - other.motion;
+    let half_size = 0.5 * (size + other_size);
+    let (t_entry, normal) = sweep_aabb(
+        body.position,
+        relative_motion,
+        other.position - half_size,
+        other.position + half_size,
+    )?;
+
+    // Report the middle of the span where the boxes touch, on the face that was hit
+    let motion = t_entry * body.motion;
+    let position = body.position + motion;
+    let other_position = other.position + t_entry * other.motion;
+    let (half_size, other_half_size) = (0.5 * size, 0.5 * other_size);
+    let start = (position - half_size).max(other_position - other_half_size);
+    let end = (position + half_size).min(other_position + other_half_size);
+    let mut position = 0.5 * (start + end);
+    let axis = if normal.x != 0 { 0 } else { 1 };
+    position[axis] = other_position[axis] + normal[axis] as f32 * other_half_size[axis];
+
+    Some(Collision {
+        motion,
+        toi: t_entry,
+        position,
+        normal: Some(normal),
+        unit_normal: Some(normal.as_vec2()),
+    })
+}
+
+fn aabb_point(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let Shape::Aabb(size) = body.shape else {
+        return None;
+    };
+
+    // Sweep the box center against the point expanded by the box's extents, using the motion
+    // relative to the point so either body may be moving
+    let half_size = 0.5 * size;
+    let (t_entry, normal) = sweep_aabb(
+        body.position,
+        body.motion - other.motion,
+        other.position - half_size,
+        other.position + half_size,
+    )?;
+
+    Some(Collision {
+        motion: t_entry * body.motion,
+        toi: t_entry,
+        position: other.position + t_entry * other.motion,
+        normal: Some(normal),
+        unit_normal: Some(normal.as_vec2()),
+    })
+}
+
+fn circle_circle(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let radius = body.shape.radius()?;
+    let other_radius = other.shape.radius()?;
+
+    // Sweep the center against a circle with both radii around the other center
+    let (t_entry, normal) = sweep_circle(
+        body.position,
+        body.motion - other.motion,
+        other.position,
+        radius + other_radius,
+    )?;
+
+    Some(Collision {
+        motion: t_entry * body.motion,
+        toi: t_entry,
+        position: other.position + t_entry * other.motion + normal * other_radius,
+        normal: Some(axis_normal(normal)),
+        unit_normal: Some(normal),
+    })
+}
+
+fn circle_aabb(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let (Shape::Circle(radius), Shape::Aabb(other_size)) = (body.shape, other.shape) else {
+        return None;
+    };
+
+    let half_size = 0.5 * other_size;
+    let (t_entry, normal) = sweep_rounded_aabb(
+        body.position,
+        body.motion - other.motion,
+        other.position - half_size,
+        other.position + half_size,
+        radius,
+    )?;
+
+    let motion = t_entry * body.motion;
+    Some(Collision {
+        motion,
+        toi: t_entry,
+        position: body.position + motion - normal * radius,
+        normal: Some(axis_normal(normal)),
+        unit_normal: Some(normal),
+    })
+}
+
+fn aabb_circle(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let (Shape::Aabb(size), Shape::Circle(other_radius)) = (body.shape, other.shape) else {
+        return None;
+    };
+
+    // Sweep the circle against the box instead and flip the normal back
+    let half_size = 0.5 * size;
+    let (t_entry, normal) = sweep_rounded_aabb(
+        other.position,
+        other.motion - body.motion,
+        body.position - half_size,
+        body.position + half_size,
+        other_radius,
+    )?;
+
+    Some(Collision {
+        motion: t_entry * body.motion,
+        toi: t_entry,
+        position: other.position + t_entry * other.motion - normal * other_radius,
+        normal: Some(axis_normal(-normal)),
+        unit_normal: Some(-normal),
+    })
+}
+
+fn point_segment(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let Shape::Segment(start, end) = other.shape else {
+        return None;
+    };
+
+    let (t_entry, normal) = sweep_segment(
+        body.position,
+        body.motion - other.motion,
+        other.position + start,
+        other.position + end,
+    )?;
+
+    let motion = t_entry * body.motion;
+    Some(Collision {
+        motion,
+        toi: t_entry,
+        position: body.position + motion,
+        normal: Some(axis_normal(normal)),
+        unit_normal: Some(normal),
+    })
+}
+
+fn aabb_segment(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let (Shape::Aabb(size), Shape::Segment(start, end)) = (body.shape, other.shape) else {
+        return None;
+    };
+
+    // Sweep the box center against the Minkowski sum of the box and the segment, which is
+    // bounded by the segment shifted by each corner and by the box around each end
+    let relative_motion = body.motion - other.motion;
+    let half_size = 0.5 * size;
+    let (start, end) = (other.position + start, other.position + end);
+    // Each shifted segment faces away from the corner it is shifted by, and segments lined up
+    // with a corner lie inside the sum
+    let edge_normal = (end - start).perp().normalize_or_zero();
+    let sides = CORNERS
+        .map(|corner| half_size * Vec2::from(corner))
+        .map(|corner| {
+            let side = edge_normal.dot(corner);
+            if side == 0.0 {
+                return None;
+            }
+            let normal = -side.signum() * edge_normal;
+            sweep_side(
+                body.position,
+                relative_motion,
+                start - corner,
+                end - corner,
+                normal,
+            )
+            .map(|(t, normal)| (t, normal, body.position + t * relative_motion + corner))
+        });
+    let ends = [start, end].map(|point| {
+        sweep_aabb(
+            body.position,
+            relative_motion,
+            point - half_size,
+            point + half_size,
+        )
+        .map(|(t, normal)| (t, normal.as_vec2(), point))
+    });
+    let (t_entry, normal, contact) = sides
+        .into_iter()
+        .chain(ends)
+        .flatten()
+        .min_by(|(t_1, ..), (t_2, ..)| t_1.total_cmp(t_2))?;
+
+    Some(Collision {
+        motion: t_entry * body.motion,
+        toi: t_entry,
+        position: contact + t_entry * other.motion,
+        normal: Some(axis_normal(normal)),
+        unit_normal: Some(normal),
+    })
+}
+
+fn circle_segment(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let (Shape::Circle(radius), Shape::Segment(start, end)) = (body.shape, other.shape) else {
+        return None;
+    };
+
+    // Sweep the circle center against the capsule around the segment, which is bounded by the
+    // segment shifted to either side and by the circle around each end
+    let relative_motion = body.motion - other.motion;
+    let (start, end) = (other.position + start, other.position + end);
+    let normal = (end - start).perp().normalize_or_zero();
+    let sides = [normal, -normal].map(|normal| {
+        let offset = normal * radius;
+        sweep_side(
+            body.position,
+            relative_motion,
+            start + offset,
+            end + offset,
+            normal,
+        )
+    });
+    let ends =
+        [start, end].map(|point| sweep_circle(body.position, relative_motion, point, radius));
+    let (t_entry, normal) = sides
+        .into_iter()
+        .chain(ends)
+        .flatten()
+        .min_by(|(t_1, _), (t_2, _)| t_1.total_cmp(t_2))?;
+
+    let motion = t_entry * body.motion;
+    Some(Collision {
+        motion,
+        toi: t_entry,
+        position: body.position + motion - normal * radius,
+        normal: Some(axis_normal(normal)),
+        unit_normal: Some(normal),
+    })
+}
+
+/// Any pair of vertex shapes where at least one is a polygon, swept with separating axes
+fn polygon_polygon(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let vertices = body.vertices()?;
+    let other_vertices = other.vertices()?;
+    let (t_entry, normal) = sweep_polygons(&vertices, &other_vertices, body.motion - other.motion)?;
+
+    let motion = t_entry * body.motion;
+    let other_motion = t_entry * other.motion;
+    let moved = Vertices::derived(vertices.iter().map(|vertex| *vertex + motion));
+    let other_moved = Vertices::derived(other_vertices.iter().map(|vertex| *vertex + other_motion));
+    Some(Collision {
+        motion,
+        toi: t_entry,
+        position: contact_point(&moved, &other_moved, normal),
+        normal: Some(axis_normal(normal)),
+        unit_normal: Some(normal),
+    })
+}
+
+/// A circle or capsule against a polygon, swept against the polygon stretched along the core
+fn round_polygon(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let (core, radius) = body.shape.rounded_core()?;
+    let vertices = other.vertices()?;
+    let (t_entry, normal) = sweep_stretched_polygon(
+        body.position,
+        body.motion - other.motion,
+        &vertices,
+        core,
+        radius,
+    )?;
+
+    let motion = t_entry * body.motion;
+    Some(Collision {
+        motion,
+        toi: t_entry,
+        position: capsule_contact(body.position + motion, core, radius, normal),
+        normal: Some(axis_normal(normal)),
+        unit_normal: Some(normal),
+    })
+}
+
+fn polygon_round(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let vertices = body.vertices()?;
+    let (other_core, other_radius) = other.shape.rounded_core()?;
+
+    // Sweep the round body against the polygon instead and flip the normal back
+    let (t_entry, normal) = sweep_stretched_polygon(
+        other.position,
+        other.motion - body.motion,
+        &vertices,
+        other_core,
+        other_radius,
+    )?;
+
+    Some(Collision {
+        motion: t_entry * body.motion,
+        toi: t_entry,
+        position: capsule_contact(
+            other.position + t_entry * other.motion,
+            other_core,
+            other_radius,
+            normal,
+        ),
+        normal: Some(axis_normal(-normal)),
+        unit_normal: Some(-normal),
+    })
+}
+
+/// The point where a capsule centered at `center` touches along `normal`, which is one end of
+/// its core unless the core lies flat against the other body
+fn capsule_contact(center: Vec2, core: Vec2, radius: f32, normal: Vec2) -> Vec2 {
+    let along = core.dot(normal);
+    let core_point = if along.abs() < 1e-6 {
+        Vec2::ZERO
+    } else {
+        -core * along.signum()
+    };
+    center + core_point - normal * radius
+}
+
+fn capsule_circle(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let (core, radius) = body.shape.capsule_core()?;
+    let other_radius = other.shape.radius()?;
+
+    // Sweep the center against the core of the capsule centered on the other circle
+    let (t_entry, normal) = sweep_rounded_polygon(
+        body.position,
+        body.motion - other.motion,
+        &[other.position - core, other.position + core],
+        radius + other_radius,
+    )?;
+
+    Some(Collision {
+        motion: t_entry * body.motion,
+        toi: t_entry,
+        position: other.position + t_entry * other.motion + normal * other_radius,
+        normal: Some(axis_normal(normal)),
+        unit_normal: Some(normal),
+    })
+}
+
+fn circle_capsule(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let radius = body.shape.radius()?;
+    let (other_core, other_radius) = other.shape.capsule_core()?;
+
+    let (t_entry, normal) = sweep_rounded_polygon(
+        body.position,
+        body.motion - other.motion,
+        &[other.position - other_core, other.position + other_core],
+        radius + other_radius,
+    )?;
+
+    let motion = t_entry * body.motion;
+    Some(Collision {
+        motion,
+        toi: t_entry,
+        position: body.position + motion - normal * radius,
+        normal: Some(axis_normal(normal)),
+        unit_normal: Some(normal),
+    })
+}
+
+fn capsule_aabb(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let (core, radius) = body.shape.capsule_core()?;
+    let Shape::Aabb(other_size) = other.shape else {
+        return None;
+    };
+
+    // Stretching the box by the core reduces the capsule to a circle
+    let half_size = 0.5 * other_size;
+    let (t_entry, normal) = sweep_rounded_aabb(
+        body.position,
+        body.motion - other.motion,
+        other.position - half_size - core,
+        other.position + half_size + core,
+        radius,
+    )?;
+
+    let motion = t_entry * body.motion;
+    let other_position = other.position + t_entry * other.motion;
+    let contact = capsule_contact(body.position + motion, core, radius, normal);
+    Some(Collision {
+        motion,
+        toi: t_entry,
+        position: contact.clamp(other_position - half_size, other_position + half_size),
+        normal: Some(axis_normal(normal)),
+        unit_normal: Some(normal),
+    })
+}
+
+fn aabb_capsule(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let Shape::Aabb(size) = body.shape else {
+        return None;
+    };
+    let (other_core, other_radius) = other.shape.capsule_core()?;
+
+    // Sweep the capsule against the box instead and flip the normal back
+    let half_size = 0.5 * size;
+    let (t_entry, normal) = sweep_rounded_aabb(
+        other.position,
+        other.motion - body.motion,
+        body.position - half_size - other_core,
+        body.position + half_size + other_core,
+        other_radius,
+    )?;
+
+    let motion = t_entry * body.motion;
+    let position = body.position + motion;
+    let contact = capsule_contact(
+        other.position + t_entry * other.motion,
+        other_core,
+        other_radius,
+        normal,
+    );
+    Some(Collision {
+        motion,
+        toi: t_entry,
+        position: contact.clamp(position - half_size, position + half_size),
+        normal: Some(axis_normal(-normal)),
+        unit_normal: Some(-normal),
+    })
+}
+
+fn capsule_segment(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let (core, radius) = body.shape.capsule_core()?;
+    let Shape::Segment(start, end) = other.shape else {
+        return None;
+    };
+
+    // Sweep the center against the segment stretched along the core and rounded by the radius
+    let (start, end) = (other.position + start, other.position + end);
+    let (t_entry, normal) = sweep_rounded_polygon(
+        body.position,
+        body.motion - other.motion,
+        &[start - core, end - core, end + core, start + core],
+        radius,
+    )?;
+
+    // Settle the contact onto the closest point of the segment
+    let motion = t_entry * body.motion;
+    let contact = capsule_contact(body.position + motion, core, radius, normal);
+    let (start, end) = (start + t_entry * other.motion, end + t_entry * other.motion);
+    let edge = end - start;
+    let ratio =
+        ((contact - start).dot(edge) / edge.length_squared().max(f32::EPSILON)).clamp(0., 1.);
+    Some(Collision {
+        motion,
+        toi: t_entry,
+        position: start + ratio * edge,
+        normal: Some(axis_normal(normal)),
+        unit_normal: Some(normal),
+    })
+}
+
+fn capsule_capsule(body: &KinematicBody, other: &KinematicBody) -> Option<Collision> {
+    let (core, radius) = body.shape.capsule_core()?;
+    let (other_core, other_radius) = other.shape.capsule_core()?;
+
+    let (t_entry, normal) = sweep_rounded_polygon(
+        body.position,
+        body.motion - other.motion,
+        &[
+            other.position - core - other_core,
+            other.position - core + other_core,
+            other.position + core + other_core,
+            other.position + core - other_core,
+        ],
+        radius + other_radius,
+    )?;
+
+    let motion = t_entry * body.motion;
+    Some(Collision {
+        motion,
+        toi: t_entry,
+        position: capsule_contact(body.position + motion, core, radius, normal),
+        normal: Some(axis_normal(normal)),
+        unit_normal: Some(normal),
+    })
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct KinematicBody {
+    pub shape: Shape,
+    pub position: Vec2,
+    pub motion: Vec2,
+    /// The layers this body collides with
+    pub mask: Flags,
+    /// The layers this body belongs to
+    pub layer: Flags,
+    /// How far short of surfaces this body stops, overriding the global skin width
+    pub skin_width: Option<f32>,
+}
+
+impl Default for KinematicBody {
+    fn default() -> Self {
+        Self {
+            shape: Shape::Point,
+            position: Vec2::ZERO,
+            motion: Vec2::ZERO,
+            mask: Flags::all(),
+            layer: Flags::all(),
+            skin_width: None,
+        }
+    }
+}
+
+impl KinematicBody {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn point(position: Vec2, motion: Vec2) -> Self {
+        Self {
+            position,
+            motion,
+            ..Default::default()
+        }
+    }
+
+    pub fn aabb(size: Vec2, position: Vec2, motion: Vec2) -> Self {
+        Self {
+            shape: Shape::Aabb(size),
+            position,
+            motion,
+            ..Default::default()
+        }
+    }
+
+    pub fn circle(radius: f32, position: Vec2, motion: Vec2) -> Self {
+        Self {
+            shape: Shape::Circle(radius),
+            position,
+            motion,
+            ..Default::default()
+        }
+    }
+
+    /// A static segment between two global points
+    pub fn segment(start: Vec2, end: Vec2) -> Self {
+        let position = 0.5 * (start + end);
+        Self {
+            shape: Shape::Segment(start - position, end - position),
+            position,
+            ..Default::default()
+        }
+    }
+
+    /// A convex polygon with vertices relative to `position`, or `None` if there are more than
+    /// `MAX_POLYGON_VERTICES`
+    pub fn polygon(
+        vertices: impl IntoIterator<Item = Vec2>,
+        position: Vec2,
+        motion: Vec2,
+    ) -> Option<Self> {
+        Some(Self {
+            shape: Shape::Polygon(Vertices::new(vertices)?),
+            position,
+            motion,
+            ..Default::default()
+        })
+    }
+
+    /// A capsule filling a box of `size`, rounded along its longer axis
+    pub fn capsule(size: Vec2, position: Vec2, motion: Vec2) -> Self {
+        Self {
+            shape: Shape::Capsule(size),
+            position,
+            motion,
+            ..Default::default()
+        }
+    }
+
+    /// A box of `size` rotated counterclockwise by `angle` in radians
+    pub fn obb(size: Vec2, angle: f32, position: Vec2, motion: Vec2) -> Self {
+        Self {
+            shape: Shape::Obb(size, angle),
+            position,
+            motion,
+            ..Default::default()
+        }
+    }
+
+    pub fn shape(mut self, shape: Shape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    pub fn size(mut self, size: Vec2) -> Self {
+        self.shape = Shape::Aabb(size);
+        self
+    }
+
+    pub fn radius(mut self, radius: f32) -> Self {
+        self.shape = Shape::Circle(radius);
+        self
+    }
+
+    pub fn capsule_size(mut self, size: Vec2) -> Self {
+        self.shape = Shape::Capsule(size);
+        self
+    }
+
+    pub fn position(mut self, position: Vec2) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn motion(mut self, motion: Vec2) -> Self {
+        self.motion = motion;
+        self
+    }
+
+    pub fn mask(mut self, mask: Flags) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    pub fn layer(mut self, layer: Flags) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn skin_width(mut self, skin_width: f32) -> Self {
+        self.skin_width = Some(skin_width);
+        self
+    }
+
+    /// Whether this body should be tested for collision against `other`
+    pub fn can_collide(&self, other: &Self, mode: FilterMode) -> bool {
+        let hits = self.mask.intersects(other.layer);
+        match mode {
+            FilterMode::Asymmetric => hits,
+            FilterMode::Symmetric => hits && other.mask.intersects(self.layer),
+        }
+    }
+
+    /// Whether the two bodies currently overlap, ignoring their motion
+    pub fn overlaps(&self, other: &Self) -> bool {
+        let offset = other.position - self.position;
+        match (self.shape, other.shape) {
+            (Shape::Point, Shape::Point) => offset == Vec2::ZERO,
+            (Shape::Circle(radius), Shape::Point) | (Shape::Point, Shape::Circle(radius)) => {
+                offset.length() < radius
+            }
+            (Shape::Circle(radius), Shape::Circle(other_radius)) => {
+                offset.length() < radius + other_radius
+            }
+            (Shape::Circle(radius), Shape::Aabb(size))
+            | (Shape::Aabb(size), Shape::Circle(radius)) => {
+                let half_size = 0.5 * size;
+                (offset.abs() - offset.abs().clamp(Vec2::ZERO, half_size)).length() < radius
+            }
+            (Shape::Aabb(size), Shape::Point) | (Shape::Point, Shape::Aabb(size)) => {
+                offset.abs().cmplt(0.5 * size).all()
+            }
+            (Shape::Aabb(size), Shape::Aabb(other_size)) => {
+                offset.abs().cmplt(0.5 * (size + other_size)).all()
+            }
+            (Shape::Capsule(_), _) => other.overlaps_capsule(self),
+            (_, Shape::Capsule(_)) => self.overlaps_capsule(other),
+            (Shape::Segment(start, end), _) => other.overlaps_segment(
+                self.position + start - other.position,
+                self.position + end - other.position,
+            ),
+            (_, Shape::Segment(start, end)) => self.overlaps_segment(
+                other.position + start - self.position,
+                other.position + end - self.position,
+            ),
+            (Shape::Circle(radius), Shape::Polygon(_) | Shape::Obb(..)) => other
+                .vertices()
+                .is_some_and(|vertices| circle_overlaps_polygon(self.position, radius, &vertices)),
+            (Shape::Polygon(_) | Shape::Obb(..), Shape::Circle(radius)) => self
+                .vertices()
+                .is_some_and(|vertices| circle_overlaps_polygon(other.position, radius, &vertices)),
+            (Shape::Polygon(_) | Shape::Obb(..), _) | (_, Shape::Polygon(_) | Shape::Obb(..)) => {
+                self.vertices()
+                    .zip(other.vertices())
+                    .is_some_and(|(vertices, other_vertices)| {
+                        polygons_overlap(&vertices, &other_vertices)
+                    })
+            }
+        }
+    }
+
+    /// Whether the capsule `other` overlaps this body
+    fn overlaps_capsule(&self, other: &Self) -> bool {
+        let Some((core, radius)) = other.shape.capsule_core() else {
+            return false;
+        };
+        let core = [other.position - core, other.position + core];
+        match self.shape {
+            Shape::Circle(self_radius) => {
+                rounded_segment_overlaps(core, radius + self_radius, &[self.position])
+            }
+            Shape::Capsule(_) => {
+                self.shape
+                    .capsule_core()
+                    .is_some_and(|(self_core, self_radius)| {
+                        let self_core = [self.position - self_core, self.position + self_core];
+                        rounded_segment_overlaps(core, radius + self_radius, &self_core)
+                    })
+            }
+            _ => self
+                .vertices()
+                .is_some_and(|vertices| rounded_segment_overlaps(core, radius, &vertices)),
+        }
+    }
+
+    /// Whether the segment between two points relative to this body overlaps it
+    fn overlaps_segment(&self, start: Vec2, end: Vec2) -> bool {
+        let edge = end - start;
+        match self.shape {
+            Shape::Point => edge.perp_dot(start) == 0. && start.dot(end) <= 0.,
+            Shape::Aabb(size) => {
+                let half_size = 0.5 * size;
+                start.abs().cmplt(half_size).all()
+                    || sweep_aabb(start, edge, -half_size, half_size).is_some()
+            }
+            Shape::Circle(radius) => {
+                // Distance from the center to the closest point of the segment
+                let ratio = (-start.dot(edge) / edge.length_squared()).clamp(0., 1.);
+                (start + ratio * edge).length() < radius || start.length() < radius
+            }
+            Shape::Segment(other_start, other_end) => {
+                sweep_segment(start, edge, other_start, other_end).is_some()
+            }
+            Shape::Polygon(vertices) => polygons_overlap(&vertices, &[start, end]),
+            Shape::Obb(..) => self.vertices().is_some_and(|vertices| {
+                polygons_overlap(&vertices, &[self.position + start, self.position + end])
+            }),
+            Shape::Capsule(_) => self.shape.capsule_core().is_some_and(|(core, radius)| {
+                rounded_segment_overlaps([-core, core], radius, &[start, end])
+            }),
+        }
+    }
+
+    pub fn corners(&self) -> Option<[Vec2; 4]> {
+        if let Shape::Aabb(size) = self.shape {
+            let half_size = size * 0.5;
+            Some([
+                self.position + half_size * Vec2::from(CORNERS[0]),
+                self.position + half_size * Vec2::from(CORNERS[1]),
+                self.position + half_size * Vec2::from(CORNERS[2]),
+                self.position + half_size * Vec2::from(CORNERS[3]),
+            ])
+        } else {
+            None
+        }
+    }
+
+    /// The shape as a convex vertex set in global space rounded by a radius
+    fn rounded_hull(&self) -> (Vertices, f32) {
+        if let Some((core, radius)) = self.shape.capsule_core() {
+            let core = Vertices::derived([self.position - core, self.position + core]);
+            return (core, radius);
+        }
+        let center = Vertices::derived([self.position]);
+        match self.shape {
+            Shape::Circle(radius) => (center, radius),
+            _ => (self.vertices().unwrap_or(center), 0.),
+        }
+    }
+
+    /// How deep this body currently is inside `other`, ignoring their motion, or `None` if they
+    /// do not overlap
+    pub fn penetration(&self, other: &Self) -> Option<Penetration> {
+        let (vertices, radius) = self.rounded_hull();
+        let (other_vertices, other_radius) = other.rounded_hull();
+        rounded_penetration((&vertices, radius), (&other_vertices, other_radius))
+    }
+
+    /// The corners of the smallest axis-aligned box around the shape
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        let half_size = match self.shape {
+            Shape::Aabb(size) | Shape::Capsule(size) => 0.5 * size,
+            Shape::Circle(radius) => Vec2::splat(radius),
+            _ => {
+                let vertices = self
+                    .vertices()
+                    .unwrap_or_else(|| Vertices::derived([self.position]));
+                return vertices
+                    .iter()
+                    .fold((self.position, self.position), |(min, max), vertex| {
+                        (min.min(*vertex), max.max(*vertex))
+                    });
+            }
+        };
+        (self.position - half_size, self.position + half_size)
+    }
+
+    /// The corners of the smallest axis-aligned box around the shape over its whole motion
+    pub fn swept_bounds(&self) -> (Vec2, Vec2) {
+        let (min, max) = self.bounds();
+        (min.min(min + self.motion), max.max(max + self.motion))
+    }
+
+    /// The global vertices of the shape, or `None` for curved shapes
+    pub fn vertices(&self) -> Option<Vertices> {
+        match self.shape {
+            Shape::Point => Some(Vertices::derived([self.position])),
+            Shape::Aabb(_) => self.corners().map(Vertices::derived),
+            Shape::Circle(_) | Shape::Capsule(_) => None,
+            Shape::Segment(start, end) => Some(Vertices::derived([
+                self.position + start,
+                self.position + end,
+            ])),
+            Shape::Polygon(vertices) => Some(Vertices::derived(
+                vertices.iter().map(|vertex| self.position + *vertex),
+            )),
+            Shape::Obb(size, angle) => {
+                let rotation = Rot2::radians(angle);
+                Some(Vertices::derived(CORNERS.iter().map(|corner| {
+                    self.position + rotation * (0.5 * size * Vec2::from(*corner))
+                })))
+            }
+        }
+    }
+
+    /// The swept collision of this body against `other`, or `None` if they do not collide or
+    /// their shapes are not supported as a pair
+    pub fn collision(&self, other: &Self) -> Option<Collision> {
+        let test = self.shape.collision_test(&other.shape)?;
+        test(self, other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_4, SQRT_2};
+
+    /// Compares the motion and position with a tolerance, since `Collision` equality only
+    /// checks the position and normal
+    fn assert_collision(actual: Option<Collision>, expected: Collision) {
+        let actual = actual.expect("expected a collision");
+        assert!(
+            actual.motion.abs_diff_eq(expected.motion, 1e-4),
+            "motion {} != {}",
+            actual.motion,
+            expected.motion
+        );
+        assert!(
+            actual.position.abs_diff_eq(expected.position, 1e-4),
+            "position {} != {}",
+            actual.position,
+            expected.position
+        );
+        assert_eq!(actual.normal, expected.normal);
+    }
+
+    #[test]
+    fn test_moving_aabb_aabb_collision() {
+        let aabb_1 = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::ONE);
+        let aabb_2 = KinematicBody::aabb(Vec2::ONE, Vec2::new(1.5, 0.75), Vec2::ZERO);
+        let actual = aabb_1.collision(&aabb_2);
+        let expected = Collision {
+            motion: Vec2::splat(0.5),
+            position: Vec2::new(1., 0.625),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_moving_aabb_aabb_perfect_collision() {
+        let aabb_1 = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::X);
+        let aabb_2 = KinematicBody::aabb(Vec2::ONE, Vec2::new(1.5, 0.), Vec2::ZERO);
+        let actual = aabb_1.collision(&aabb_2);
+        let expected = Collision {
+            motion: Vec2::new(0.5, 0.),
+            position: Vec2::new(1., 0.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_moving_aabb_thin_aabb_collision() {
+        let aabb_1 = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::new(2., 0.));
+        let aabb_2 = KinematicBody::aabb(Vec2::new(0.2, 0.2), Vec2::new(1.5, 0.), Vec2::ZERO);
+        let actual = aabb_1.collision(&aabb_2);
+        let expected = Collision {
+            motion: Vec2::new(0.9, 0.),
+            position: Vec2::new(1.4, 0.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_moving_aabb_aabb_non_collision() {
+        let aabb_1 = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::ONE);
+        let aabb_2 = KinematicBody::aabb(Vec2::ONE, Vec2::new(2.5, 0.75), Vec2::ZERO);
+        let actual = aabb_1.collision(&aabb_2);
+        let expected = None;
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_moving_aabb_moving_aabb_collision() {
+        let aabb_1 = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::X);
+        let aabb_2 = KinematicBody::aabb(Vec2::ONE, Vec2::new(2., 0.25), -Vec2::X);
+        let actual = aabb_1.collision(&aabb_2);
+        let expected = Collision {
+            motion: Vec2::new(0.5, 0.),
+            position: Vec2::new(1., 0.125),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_moving_aabb_moving_aabb_chase_collision() {
+        let aabb_1 = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::new(0., 2.));
+        let aabb_2 = KinematicBody::aabb(Vec2::ONE, Vec2::new(0., 1.5), Vec2::Y);
+        let actual = aabb_1.collision(&aabb_2);
+        let expected = Collision {
+            motion: Vec2::new(0., 1.),
+            position: Vec2::new(0., 1.5),
+            normal: Some(-IVec2::Y),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_moving_aabb_moving_aabb_non_collision() {
+        let aabb_1 = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::X);
+        let aabb_2 = KinematicBody::aabb(Vec2::ONE, Vec2::new(1.5, 0.), Vec2::X);
+        let actual = aabb_1.collision(&aabb_2);
+        let expected = None;
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_point_point_collision() {
+        let point_1 = KinematicBody::point(Vec2::ZERO, Vec2::ONE);
+        let point_2 = KinematicBody::point(Vec2::new(0.5, 0.), Vec2::Y);
+        let actual = point_1.collision(&point_2);
+        let expected = Collision {
+            motion: Vec2::splat(0.5),
+            position: Vec2::splat(0.5),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_point_point_diagonal_normal() {
+        let point_1 = KinematicBody::point(Vec2::new(0., 1.), Vec2::new(0., -2.));
+        let point_2 = KinematicBody::point(Vec2::new(-1., -1.), Vec2::new(2., 2.));
+        let actual = point_1.collision(&point_2).unwrap();
+        assert!(actual.position.abs_diff_eq(Vec2::ZERO, 1e-5));
+        assert!(actual
+            .unit_normal
+            .unwrap()
+            .abs_diff_eq(Vec2::new(-1., 1.).normalize(), 1e-5));
+    }
+
+    #[test]
+    fn test_point_point_non_collision() {
+        let point_1 = KinematicBody::point(Vec2::ZERO, Vec2::ONE);
+        let point_2 = KinematicBody::point(Vec2::new(0.5, 0.), Vec2::ONE);
+        let actual = point_1.collision(&point_2);
+        let expected = None;
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_aabb_point_collision() {
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::ONE);
+        let point = KinematicBody::point(Vec2::new(1., 0.75), Vec2::ZERO);
+        let actual = aabb.collision(&point);
+        let expected = Collision {
+            motion: Vec2::splat(0.5),
+            position: Vec2::new(1., 0.75),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_aabb_moving_point_collision() {
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::ZERO);
+        let point = KinematicBody::point(Vec2::new(0.25, 1.), -Vec2::Y);
+        let actual = aabb.collision(&point);
+        let expected = Collision {
+            motion: Vec2::ZERO,
+            position: Vec2::new(0.25, 0.5),
+            normal: Some(-IVec2::Y),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_point_moving_aabb_collision() {
+        let point = KinematicBody::point(Vec2::ZERO, Vec2::X);
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::new(3., 0.), Vec2::new(-2., 0.));
+        let expected = Collision {
+            motion: Vec2::new(2.5 / 3., 0.),
+            position: Vec2::new(2.5 / 3., 0.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(point.collision(&aabb), expected);
+
+        // Either body asked first agrees on the time of impact
+        let mirrored = aabb.collision(&point).unwrap();
+        assert!((mirrored.toi - point.collision(&aabb).unwrap().toi).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_point_aabb_along_axis_collision() {
+        let point = KinematicBody::point(Vec2::new(0., 0.25), Vec2::new(0., 2.));
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::new(0., 2.), Vec2::ZERO);
+        let actual = point.collision(&aabb).unwrap();
+        assert!(actual.motion.abs_diff_eq(Vec2::new(0., 1.25), 1e-6));
+        assert!(actual.toi.is_finite());
+        assert_eq!(actual.normal, Some(-IVec2::Y));
+    }
+
+    #[test]
+    fn test_aabb_point_non_collision() {
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::ONE);
+        let point = KinematicBody::point(Vec2::new(1., -0.75), Vec2::ZERO);
+        let actual = aabb.collision(&point);
+        let expected = None;
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_point_aabb_collision() {
+        let point = KinematicBody::point(Vec2::ZERO, Vec2::ONE);
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::new(1., 0.75), Vec2::ZERO);
+        let actual = point.collision(&aabb);
+        let expected = Collision {
+            motion: Vec2::splat(0.5),
+            position: Vec2::splat(0.5),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_point_aabb_non_collision() {
+        let point = KinematicBody::point(Vec2::ZERO, Vec2::ONE);
+        let aabb = KinematicBody::aabb(Vec2::new(1., 0.25), Vec2::new(1.5, 0.), Vec2::ZERO);
+        let actual = point.collision(&aabb);
+        let expected = None;
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_shape_builders() {
+        let aabb = KinematicBody::new().size(Vec2::ONE);
+        assert_eq!(aabb.shape, Shape::Aabb(Vec2::ONE));
+        let circle = KinematicBody::new().shape(Shape::Circle(0.5));
+        assert_eq!(circle.shape, KinematicBody::new().radius(0.5).shape);
+        assert_eq!(
+            KinematicBody::point(Vec2::ZERO, Vec2::ONE).shape,
+            Shape::Point
+        );
+    }
+
+    #[test]
+    fn test_collision_test_dispatch() {
+        let shapes = [Shape::Point, Shape::Aabb(Vec2::ONE), Shape::Circle(0.5)];
+        for shape in &shapes {
+            for other in &shapes {
+                assert!(
+                    shape.collision_test(other).is_some(),
+                    "{shape:?} against {other:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_circle_circle_collision() {
+        let circle_1 = KinematicBody::circle(0.5, Vec2::ZERO, Vec2::new(2., 0.));
+        let circle_2 = KinematicBody::circle(0.5, Vec2::new(2., 0.), Vec2::ZERO);
+        let actual = circle_1.collision(&circle_2);
+        let expected = Collision {
+            motion: Vec2::new(1., 0.),
+            position: Vec2::new(1.5, 0.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_circle_circle_glancing_collision() {
+        let circle_1 = KinematicBody::circle(0.5, Vec2::ZERO, Vec2::new(2., 0.));
+        let circle_2 = KinematicBody::circle(0.5, Vec2::new(2., 0.6), Vec2::ZERO);
+        let actual = circle_1.collision(&circle_2).unwrap();
+        assert!(actual.motion.abs_diff_eq(Vec2::new(1.2, 0.), 1e-5));
+        assert!(actual.position.abs_diff_eq(Vec2::new(1.6, 0.3), 1e-5));
+        assert!(actual
+            .unit_normal
+            .unwrap()
+            .abs_diff_eq(Vec2::new(-0.8, -0.6), 1e-5));
+        assert_eq!(actual.normal, Some(-IVec2::X));
+    }
+
+    #[test]
+    fn test_circle_circle_non_collision() {
+        let circle_1 = KinematicBody::circle(0.5, Vec2::ZERO, Vec2::new(2., 0.));
+        let circle_2 = KinematicBody::circle(0.5, Vec2::new(2., 1.5), Vec2::ZERO);
+        let actual = circle_1.collision(&circle_2);
+        let expected = None;
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_point_circle_collision() {
+        let point = KinematicBody::point(Vec2::ZERO, Vec2::new(2., 0.));
+        let circle = KinematicBody::circle(0.5, Vec2::new(2., 0.), Vec2::ZERO);
+        let actual = point.collision(&circle);
+        let expected = Collision {
+            motion: Vec2::new(1.5, 0.),
+            position: Vec2::new(1.5, 0.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_circle_aabb_collision() {
+        let circle = KinematicBody::circle(0.5, Vec2::ZERO, Vec2::new(2., 0.));
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::new(2., 0.25), Vec2::ZERO);
+        let actual = circle.collision(&aabb);
+        let expected = Collision {
+            motion: Vec2::new(1., 0.),
+            position: Vec2::new(1.5, 0.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_circle_aabb_corner_collision() {
+        let circle = KinematicBody::circle(0.5, Vec2::ZERO, Vec2::splat(2.));
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::splat(2.), Vec2::ZERO);
+        let actual = circle.collision(&aabb).unwrap();
+        assert!(actual.position.abs_diff_eq(Vec2::splat(1.5), 1e-5));
+        assert!(actual
+            .unit_normal
+            .unwrap()
+            .abs_diff_eq(-Vec2::ONE.normalize(), 1e-5));
+    }
+
+    #[test]
+    fn test_aabb_circle_collision() {
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::new(2., 0.));
+        let circle = KinematicBody::circle(0.5, Vec2::new(2., 0.), Vec2::ZERO);
+        let actual = aabb.collision(&circle);
+        let expected = Collision {
+            motion: Vec2::new(1., 0.),
+            position: Vec2::new(1.5, 0.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
 
-#[derive(Debug, Default, Copy, Clone)]
-/// A point of collision between two objects
-pub struct Collision {
-    /// The resolved motion of the collision
-    pub motion: Vec2,
-    /// The global position at which the objects collision
-    pub position: Vec2,
-    /// The optional normal unit vector of the collision
-    pub normal: Option<IVec2>,
-}
+    #[test]
+    fn test_axis_normal() {
+        assert_eq!(axis_normal(Vec2::new(-0.8, 0.6)), -IVec2::X);
+        assert_eq!(axis_normal(Vec2::new(0.6, 0.8)), IVec2::Y);
+        assert_eq!(axis_normal(Vec2::ZERO), IVec2::ZERO);
+    }
 
-impl PartialEq for Collision {
-    fn eq(&self, other: &Self) -> bool {
-        self.position == other.position
-            && self.normal == other.normal
-            && (self.position.x - other.position.x).abs() < 0.001
-            && (self.position.y - other.position.y).abs() < 0.001
+    #[test]
+    fn test_circle_aabb_overlap() {
+        let circle = KinematicBody::circle(0.5, Vec2::ZERO, Vec2::ZERO);
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::splat(0.8), Vec2::ZERO);
+        assert!(circle.overlaps(&aabb));
+        let aabb = aabb.position(Vec2::splat(1.));
+        assert!(!circle.overlaps(&aabb));
     }
-}
 
-const CORNERS: [[f32; 2]; 4] = [[-1., -1.], [-1., 1.], [1., 1.], [1., -1.]];
+    #[test]
+    fn test_point_segment_collision() {
+        let point = KinematicBody::point(Vec2::ZERO, Vec2::new(2., 0.));
+        let segment = KinematicBody::segment(Vec2::new(1., -1.), Vec2::new(1., 1.));
+        let actual = point.collision(&segment);
+        let expected = Collision {
+            motion: Vec2::new(1., 0.),
+            position: Vec2::new(1., 0.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
 
-#[derive(Component, Clone, Debug, Default)]
-pub struct KinematicBody {
-    pub size: Option<Vec2>,
-    pub position: Vec2,
-    pub motion: Vec2,
-    pub mask: Flags,
-    pub layer: Flags,
-}
+    #[test]
+    fn test_point_diagonal_segment_collision() {
+        let point = KinematicBody::point(Vec2::ZERO, Vec2::new(2., 0.));
+        let segment = KinematicBody::segment(Vec2::new(1., -1.), Vec2::new(2., 1.));
+        let actual = point.collision(&segment).unwrap();
+        assert!(actual.position.abs_diff_eq(Vec2::new(1.5, 0.), 1e-5));
+        assert!(actual
+            .unit_normal
+            .unwrap()
+            .abs_diff_eq(Vec2::new(-2., 1.).normalize(), 1e-5));
+        assert_eq!(actual.normal, Some(-IVec2::X));
+    }
 
-impl KinematicBody {
-    pub fn new() -> Self {
-        Self::default()
+    #[test]
+    fn test_point_segment_non_collision() {
+        let point = KinematicBody::point(Vec2::ZERO, Vec2::new(0., 2.));
+        let segment = KinematicBody::segment(Vec2::new(1., -1.), Vec2::new(1., 1.));
+        let actual = point.collision(&segment);
+        let expected = None;
+        assert_eq!(actual, expected);
     }
 
-    pub fn point(position: Vec2, motion: Vec2) -> Self {
-        Self {
-            position,
-            motion,
+    #[test]
+    fn test_aabb_short_segment_collision() {
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::new(2., 0.));
+        let segment = KinematicBody::segment(Vec2::new(1.5, -0.2), Vec2::new(1.5, 0.2));
+        let actual = aabb.collision(&segment);
+        let expected = Collision {
+            motion: Vec2::new(1., 0.),
+            position: Vec2::new(1.5, -0.2),
+            normal: Some(-IVec2::X),
             ..Default::default()
-        }
+        };
+        assert_collision(actual, expected);
     }
 
-    pub fn aabb(size: Vec2, position: Vec2, motion: Vec2) -> Self {
-        Self {
-            size: Some(size),
-            position,
-            motion,
+    #[test]
+    fn test_aabb_long_segment_collision() {
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::new(2., 0.));
+        let segment = KinematicBody::segment(Vec2::new(1.5, -1.), Vec2::new(1.5, 1.));
+        let actual = aabb.collision(&segment).unwrap();
+        assert_eq!(actual.motion, Vec2::new(1., 0.));
+        assert_eq!(actual.unit_normal, Some(-Vec2::X));
+    }
+
+    #[test]
+    fn test_circle_segment_collision() {
+        let circle = KinematicBody::circle(0.5, Vec2::ZERO, Vec2::new(2., 0.));
+        let segment = KinematicBody::segment(Vec2::new(1.5, -1.), Vec2::new(1.5, 1.));
+        let actual = circle.collision(&segment);
+        let expected = Collision {
+            motion: Vec2::new(1., 0.),
+            position: Vec2::new(1.5, 0.),
+            normal: Some(-IVec2::X),
             ..Default::default()
-        }
+        };
+        assert_collision(actual, expected);
     }
 
-    pub fn size(mut self, size: Vec2) -> Self {
-        self.size = Some(size);
-        self
+    #[test]
+    fn test_circle_segment_end_collision() {
+        let circle = KinematicBody::circle(0.5, Vec2::ZERO, Vec2::new(2., 0.));
+        let segment = KinematicBody::segment(Vec2::new(1.5, 0.3), Vec2::new(1.5, 2.));
+        let actual = circle.collision(&segment).unwrap();
+        assert!(actual.motion.abs_diff_eq(Vec2::new(1.1, 0.), 1e-5));
+        assert!(actual.position.abs_diff_eq(Vec2::new(1.5, 0.3), 1e-5));
+        assert!(actual
+            .unit_normal
+            .unwrap()
+            .abs_diff_eq(Vec2::new(-0.8, -0.6), 1e-5));
     }
 
-    pub fn position(mut self, position: Vec2) -> Self {
-        self.position = position;
-        self
+    #[test]
+    fn test_touching_segment_moving_away_non_collision() {
+        let segment = KinematicBody::segment(Vec2::new(1., -1.), Vec2::new(1., 1.));
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::new(0.5, 0.), -Vec2::X);
+        assert_eq!(aabb.collision(&segment), None);
+        let circle = KinematicBody::circle(0.5, Vec2::new(0.5, 0.), Vec2::new(-1., 1.));
+        assert_eq!(circle.collision(&segment), None);
+        let capsule = KinematicBody::capsule(Vec2::new(1., 2.), Vec2::new(0.5, 0.), -Vec2::X);
+        assert_eq!(capsule.collision(&segment), None);
+
+        // Touching bodies are still blocked moving into the segment
+        let actual = aabb.motion(Vec2::X).collision(&segment).unwrap();
+        assert_eq!(actual.toi, 0.);
+        assert_eq!(actual.motion, Vec2::ZERO);
     }
 
-    pub fn motion(mut self, motion: Vec2) -> Self {
-        self.motion = motion;
-        self
+    #[test]
+    fn test_segment_unsupported_collision() {
+        let segment = KinematicBody::segment(Vec2::ZERO, Vec2::ONE).motion(Vec2::X);
+        let point = KinematicBody::point(Vec2::new(1., 0.5), Vec2::ZERO);
+        assert!(segment.shape.collision_test(&point.shape).is_none());
+        assert_eq!(segment.collision(&point), None);
     }
 
-    pub fn corners(&self) -> Option<[Vec2; 4]> {
-        if let Some(size) = self.size {
-            let half_size = size * 0.5;
-            Some([
-                self.position + half_size * Vec2::from(CORNERS[0]),
-                self.position + half_size * Vec2::from(CORNERS[1]),
-                self.position + half_size * Vec2::from(CORNERS[2]),
-                self.position + half_size * Vec2::from(CORNERS[3]),
-            ])
-        } else {
-            None
-        }
+    #[test]
+    fn test_segment_overlap() {
+        let segment = KinematicBody::segment(Vec2::new(-1., 1.), Vec2::new(1., 1.));
+        let circle = KinematicBody::circle(0.5, Vec2::new(0., 0.75), Vec2::ZERO);
+        assert!(segment.overlaps(&circle));
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::ZERO);
+        assert!(!aabb.overlaps(&segment));
+        assert!(aabb.position(Vec2::new(0.9, 0.9)).overlaps(&segment));
     }
 
-    pub fn collision(&self, other: &Self) -> Option<Collision> {
-        match (self.size, other.size) {
-            // Point-point collision
-            (None, None) => {
-                let cross = self.motion.perp_dot(other.motion);
-                if cross == 0.0 {
-                    return None; // Lines are parallel or collinear
-                }
-
-                let pos_displacement = other.position - self.position;
-
-                let self_ratio = pos_displacement.perp_dot(other.motion) / cross;
-                let other_ratio = pos_displacement.perp_dot(self.motion) / cross;
-
-                // Check if the collision point lies on both line segments
-                if (0.0..=1.0).contains(&self_ratio) && (0.0..1.0).contains(&other_ratio) {
-                    let motion = self_ratio * self.motion;
-                    Some(Collision {
-                        motion,
-                        position: self.position + motion,
-                        ..Default::default()
-                    })
-                } else {
-                    None // No collision within the line segments
-                }
-            }
-            // Point-AABB collision
-            (None, Some(other_size)) => {
-                let inv_displacement = Vec2::new(1.0 / self.motion.x, 1.0 / self.motion.y);
-
-                // Compute the t-values for intersections with the AABB's boundaries
-                let t_min = (other.position - 0.5 * other_size - self.position) * inv_displacement;
-                let t_max = (other.position + 0.5 * other_size - self.position) * inv_displacement;
-                // println!(
-                //     "other.position: {:?}, other_size: {:?}, self.position: {:?}, t_min: {:?}, t_max: {:?}",
-                //     other.position, other_size, self.position, t_min, t_max
-                // );
-
-                // Determine the near and far t-values for each axis
-                let t_near = t_min.min(t_max);
-                let t_far = t_min.max(t_max);
-
-                // Find the largest t_near and smallest t_far
-                let t_entry = t_near.x.max(t_near.y);
-                let t_exit = t_far.x.min(t_far.y);
-
-                // println!(
-                //     "{:?}, {:?}, {:?}, {:?}, {:?}, {:?}, {:?}",
-                //     inv_displacement, t_min, t_max, t_near, t_far, t_entry, t_exit
-                // );
-
-                // If the line misses the AABB or the collision is outside the line segment, return None
-                if t_entry > t_exit || t_exit <= 0.0 || t_entry > 1.0 {
-                    return None;
-                }
-
-                // Determine the collision side based on the t_entry axis
-                let normal = if t_entry == t_near.x {
-                    Some(IVec2::new(-self.motion.x.signum() as i32, 0))
-                } else {
-                    Some(IVec2::new(0, -self.motion.y.signum() as i32))
-                };
-
-                // Compute the collision point
-                let motion = t_entry * self.motion;
-                let position = self.position + motion;
-
-                Some(Collision {
-                    motion,
-                    position,
-                    normal,
-                })
-            }
-            // AABB-AABB collision
-            (Some(_), Some(_)) => {
-                if other.motion != Vec2::ZERO {
-                    return None;
-                }
-                let mut min_collision: Option<Collision> = None;
-                let mut min_distance = f32::INFINITY;
-                for corner in self.corners().unwrap() {
-                    let point = KinematicBody::point(corner, self.motion);
-                    if let Some(collision) = point.collision(other) {
-                        let distance = collision.position.distance(corner);
-                        if distance < min_distance {
-                            min_collision = Some(collision);
-                            min_distance = distance;
-                        }
-                    }
-                }
-                min_collision
-            }
-            // TODO: AABB-point collision
-            _ => None,
-        }
+    #[test]
+    fn test_aabb_aabb_overlap() {
+        let aabb_1 = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::ZERO);
+        let aabb_2 = KinematicBody::aabb(Vec2::ONE, Vec2::new(0.5, 0.9), Vec2::ZERO);
+        assert!(aabb_1.overlaps(&aabb_2));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_aabb_aabb_touching_non_overlap() {
+        let aabb_1 = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::ZERO);
+        let aabb_2 = KinematicBody::aabb(Vec2::ONE, Vec2::new(1., 0.), Vec2::ZERO);
+        assert!(!aabb_1.overlaps(&aabb_2));
+    }
 
-    // #[test]
-    // fn test_moving_aabb_aabb_collision() {
-    //     let aabb_1 = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::ONE);
-    //     let aabb_2 = KinematicBody::aabb(Vec2::ONE, Vec2::new(1.5, 0.75), Vec2::ZERO);
-    //     let actual = aabb_1.collision(&aabb_2);
-    //     let expected = Some(Collision {
-    //         motion: Vec2::splat(0.5),
-    //         position: Vec2::splat(1.5),
-    //         normal: Some(-IVec2::X),
-    //     });
-    //     assert_eq!(actual, expected);
-    // }
-
-    // #[test]
-    // fn test_moving_aabb_aabb_perfect_collision() {
-    //     let aabb_1 = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::X);
-    //     let aabb_2 = KinematicBody::aabb(Vec2::ONE, Vec2::new(1.5, 0.), Vec2::ZERO);
-    //     let actual = aabb_1.collision(&aabb_2);
-    //     let expected = Some(Collision {
-    //         motion: Vec2::new(0.5, 0.),
-    //         position: Vec2::new(1.5, 0.),
-    //         normal: Some(-IVec2::X),
-    //     });
-    //     assert_eq!(actual, expected);
-    // }
+    #[test]
+    fn test_aabb_point_overlap() {
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::ZERO);
+        assert!(aabb.overlaps(&KinematicBody::point(Vec2::splat(0.25), Vec2::ZERO)));
+        assert!(!aabb.overlaps(&KinematicBody::point(Vec2::new(0.75, 0.), Vec2::ZERO)));
+    }
 
     #[test]
-    fn test_moving_aabb_aabb_non_collision() {
-        let aabb_1 = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::ONE);
-        let aabb_2 = KinematicBody::aabb(Vec2::ONE, Vec2::new(2.5, 0.75), Vec2::ZERO);
-        let actual = aabb_1.collision(&aabb_2);
-        let expected = None;
-        assert_eq!(actual, expected);
+    fn test_time_of_impact() {
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::new(4., 0.));
+        let other = KinematicBody::aabb(Vec2::ONE, Vec2::new(2., 0.), Vec2::ZERO);
+        assert_eq!(aabb.collision(&other).map(|c| c.toi), Some(0.25));
+        let circle = KinematicBody::circle(0.5, Vec2::ZERO, Vec2::new(2., 0.));
+        let other = KinematicBody::circle(0.5, Vec2::new(2., 0.), Vec2::ZERO);
+        assert_eq!(circle.collision(&other).map(|c| c.toi), Some(0.5));
+        let point = KinematicBody::point(Vec2::ZERO, Vec2::ONE);
+        let other = KinematicBody::point(Vec2::new(0.5, 0.), Vec2::Y);
+        assert_eq!(point.collision(&other).map(|c| c.toi), Some(0.5));
+        let polygon = KinematicBody::polygon(
+            vec![Vec2::ZERO, Vec2::X, Vec2::Y],
+            Vec2::ZERO,
+            Vec2::new(4., 0.),
+        )
+        .unwrap();
+        let other = KinematicBody::aabb(Vec2::ONE, Vec2::new(3.5, 0.), Vec2::ZERO);
+        assert_eq!(polygon.collision(&other).map(|c| c.toi), Some(0.5));
     }
 
     #[test]
-    fn test_point_point_collision() {
-        let point_1 = KinematicBody::point(Vec2::ZERO, Vec2::ONE);
-        let point_2 = KinematicBody::point(Vec2::new(0.5, 0.), Vec2::Y);
-        let actual = point_1.collision(&point_2);
-        let expected = Some(Collision {
-            motion: Vec2::splat(0.5),
-            position: Vec2::splat(0.5),
-            ..Default::default()
+    fn test_aabb_aabb_penetration() {
+        let aabb_1 = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::ZERO);
+        let aabb_2 = KinematicBody::aabb(Vec2::ONE, Vec2::new(0.75, 0.25), Vec2::ZERO);
+        let actual = aabb_1.penetration(&aabb_2);
+        let expected = Some(Penetration {
+            depth: 0.25,
+            normal: -Vec2::X,
         });
         assert_eq!(actual, expected);
+        assert_eq!(actual.unwrap().mtv(), Vec2::new(-0.25, 0.));
     }
 
     #[test]
-    fn test_point_point_non_collision() {
-        let point_1 = KinematicBody::point(Vec2::ZERO, Vec2::ONE);
-        let point_2 = KinematicBody::point(Vec2::new(0.5, 0.), Vec2::ONE);
-        let actual = point_1.collision(&point_2);
-        let expected = None;
-        assert_eq!(actual, expected);
+    fn test_circle_penetration() {
+        let circle = KinematicBody::circle(1., Vec2::ZERO, Vec2::ZERO);
+        let other = KinematicBody::circle(1., Vec2::new(0.6, 0.8), Vec2::ZERO);
+        let actual = circle.penetration(&other).unwrap();
+        assert!((actual.depth - 1.).abs() < 1e-5);
+        assert!(actual.normal.abs_diff_eq(Vec2::new(-0.6, -0.8), 1e-5));
+        let aabb = KinematicBody::aabb(Vec2::splat(2.), Vec2::new(1.5, 0.), Vec2::ZERO);
+        let actual = circle.penetration(&aabb).unwrap();
+        assert!((actual.depth - 0.5).abs() < 1e-5);
+        assert!(actual.normal.abs_diff_eq(-Vec2::X, 1e-5));
+    }
+
+    #[test]
+    fn test_touching_non_penetration() {
+        let aabb_1 = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::ZERO);
+        let aabb_2 = KinematicBody::aabb(Vec2::ONE, Vec2::new(1., 0.), Vec2::ZERO);
+        assert_eq!(aabb_1.penetration(&aabb_2), None);
+        let circle = KinematicBody::circle(0.5, Vec2::new(0., 2.), Vec2::ZERO);
+        assert_eq!(circle.penetration(&aabb_1), None);
+    }
+
+    #[test]
+    fn test_skin_width() {
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::new(2., 0.));
+        let wall = KinematicBody::aabb(Vec2::ONE, Vec2::new(2., 0.), Vec2::ZERO);
+        let collision = aabb.collision(&wall).unwrap().with_skin(aabb.motion, 0.1);
+        assert!(collision.motion.abs_diff_eq(Vec2::new(0.9, 0.), 1e-5));
+        assert!((collision.toi - 0.45).abs() < 1e-5);
+
+        // Already within the skin, the body does not move any closer
+        let aabb = aabb.position(Vec2::new(0.95, 0.));
+        let collision = aabb.collision(&wall).unwrap().with_skin(aabb.motion, 0.1);
+        assert_eq!(collision.motion, Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_skin_width_diagonal() {
+        let point = KinematicBody::point(Vec2::ZERO, Vec2::new(2., -2.));
+        let floor = KinematicBody::aabb(Vec2::new(4., 1.), Vec2::new(0., -1.5), Vec2::ZERO);
+        let collision = point
+            .collision(&floor)
+            .unwrap()
+            .with_skin(point.motion, 0.1);
+        assert!(collision.motion.abs_diff_eq(Vec2::new(1., -0.9), 1e-5));
+        // The motion along the floor is kept, so the body gets further than 0.45 of the way
+        assert!((collision.toi - 0.475).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_skin_width_shallow() {
+        // Gliding almost parallel to the floor, the body keeps moving along it
+        let point = KinematicBody::point(Vec2::new(0., 0.05), Vec2::new(4., -0.1));
+        let floor = KinematicBody::aabb(Vec2::new(8., 1.), Vec2::new(0., -0.5), Vec2::ZERO);
+        let collision = point
+            .collision(&floor)
+            .unwrap()
+            .with_skin(point.motion, 0.1);
+        assert!(collision.motion.abs_diff_eq(Vec2::new(2., 0.), 1e-5));
+        assert!((collision.toi - 8. / point.motion.length_squared()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_bounds() {
+        let circle = KinematicBody::circle(0.5, Vec2::ONE, Vec2::new(-2., 1.));
+        assert_eq!(circle.bounds(), (Vec2::splat(0.5), Vec2::splat(1.5)));
+        assert_eq!(
+            circle.swept_bounds(),
+            (Vec2::new(-1.5, 0.5), Vec2::new(1.5, 2.5))
+        );
+        let segment = KinematicBody::segment(Vec2::new(2., -1.), Vec2::new(-2., 3.));
+        assert_eq!(segment.bounds(), (Vec2::new(-2., -1.), Vec2::new(2., 3.)));
+        let ramp = KinematicBody::polygon(
+            vec![Vec2::ZERO, Vec2::new(2., 0.), Vec2::new(0., 2.)],
+            Vec2::new(1., 1.),
+            Vec2::ZERO,
+        )
+        .unwrap();
+        assert_eq!(ramp.bounds(), (Vec2::ONE, Vec2::splat(3.)));
+    }
+
+    #[test]
+    fn test_mirrored_collision() {
+        let collision = Collision {
+            motion: Vec2::X,
+            toi: 0.5,
+            position: Vec2::ONE,
+            normal: Some(-IVec2::X),
+            unit_normal: Some(-Vec2::X),
+        };
+        let expected = Collision {
+            normal: Some(IVec2::X),
+            unit_normal: Some(Vec2::X),
+            ..collision
+        };
+        assert_eq!(collision.mirrored(), expected);
+    }
+
+    #[test]
+    fn test_stop_response() {
+        let actual = CollisionResponse::Stop.resolve(Vec2::ONE, Some(-Vec2::X));
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn test_slide_response() {
+        let actual = CollisionResponse::Slide.resolve(Vec2::new(1., 0.5), Some(-Vec2::X));
+        assert_eq!(actual, Some(Vec2::new(0., 0.5)));
+    }
+
+    #[test]
+    fn test_slide_response_without_normal() {
+        let actual = CollisionResponse::Slide.resolve(Vec2::ONE, None);
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn test_bounce_response() {
+        let response = CollisionResponse::Bounce { restitution: 1. };
+        let actual = response.resolve(Vec2::new(1., 0.5), Some(-Vec2::X));
+        assert_eq!(actual, Some(Vec2::new(-1., 0.5)));
+    }
+
+    #[test]
+    fn test_inelastic_bounce_response() {
+        let response = CollisionResponse::Bounce { restitution: 0.5 };
+        let actual = response.resolve(Vec2::new(0.5, -1.), Some(Vec2::Y));
+        assert_eq!(actual, Some(Vec2::new(0.5, 0.5)));
+    }
+
+    #[test]
+    fn test_pass_through_response() {
+        let actual = CollisionResponse::PassThrough.resolve(Vec2::ONE, None);
+        assert_eq!(actual, Some(Vec2::ONE));
+    }
+
+    #[test]
+    fn test_default_can_collide() {
+        let body_1 = KinematicBody::new();
+        let body_2 = KinematicBody::new();
+        assert!(body_1.can_collide(&body_2, FilterMode::Symmetric));
+    }
+
+    #[test]
+    fn test_asymmetric_can_collide() {
+        let projectile = KinematicBody::new().layer(Flags::A).mask(Flags::B);
+        let enemy = KinematicBody::new().layer(Flags::B).mask(Flags::C);
+        assert!(projectile.can_collide(&enemy, FilterMode::Asymmetric));
+        assert!(!enemy.can_collide(&projectile, FilterMode::Asymmetric));
+    }
+
+    #[test]
+    fn test_symmetric_can_collide() {
+        let projectile = KinematicBody::new().layer(Flags::A).mask(Flags::B);
+        let enemy = KinematicBody::new().layer(Flags::B).mask(Flags::C);
+        assert!(!projectile.can_collide(&enemy, FilterMode::Symmetric));
+        let enemy = enemy.mask(Flags::A | Flags::C);
+        assert!(projectile.can_collide(&enemy, FilterMode::Symmetric));
+    }
+
+    #[test]
+    fn test_point_polygon_collision() {
+        let point = KinematicBody::point(Vec2::new(0., 1.), Vec2::new(4., 0.));
+        let ramp = KinematicBody::polygon(
+            vec![Vec2::ZERO, Vec2::new(2., 0.), Vec2::new(0., 2.)],
+            Vec2::new(2., 0.),
+            Vec2::ZERO,
+        )
+        .unwrap();
+        let actual = point.collision(&ramp);
+        let expected = Collision {
+            motion: Vec2::new(2., 0.),
+            position: Vec2::new(2., 1.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_aabb_polygon_collision() {
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::new(2., 0.));
+        let diamond = KinematicBody::polygon(
+            vec![Vec2::X, Vec2::Y, -Vec2::X, -Vec2::Y],
+            Vec2::new(3., 0.),
+            Vec2::ZERO,
+        )
+        .unwrap();
+        let actual = aabb.collision(&diamond);
+        let expected = Collision {
+            motion: Vec2::new(1.5, 0.),
+            position: Vec2::new(2., 0.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_polygon_polygon_slope_collision() {
+        let diamond = KinematicBody::polygon(
+            vec![
+                Vec2::new(0.5, 0.),
+                Vec2::new(0., 0.5),
+                Vec2::new(-0.5, 0.),
+                Vec2::new(0., -0.5),
+            ],
+            Vec2::new(1., 3.),
+            Vec2::new(0., -2.),
+        )
+        .unwrap();
+        let ramp = KinematicBody::polygon(
+            vec![Vec2::ZERO, Vec2::new(4., 0.), Vec2::new(4., 4.)],
+            Vec2::ZERO,
+            Vec2::ZERO,
+        )
+        .unwrap();
+        let actual = diamond.collision(&ramp).unwrap();
+        assert!(actual.motion.abs_diff_eq(Vec2::new(0., -1.5), 1e-5));
+        assert!(actual.position.abs_diff_eq(Vec2::new(1.25, 1.25), 1e-5));
+        assert!(actual
+            .unit_normal
+            .unwrap()
+            .abs_diff_eq(Vec2::new(-1., 1.).normalize(), 1e-5));
+    }
+
+    #[test]
+    fn test_point_polygon_non_collision() {
+        let point = KinematicBody::point(Vec2::new(0., 3.), Vec2::new(4., 0.));
+        let ramp = KinematicBody::polygon(
+            vec![Vec2::ZERO, Vec2::new(2., 0.), Vec2::new(0., 2.)],
+            Vec2::new(2., 0.),
+            Vec2::ZERO,
+        )
+        .unwrap();
+        assert_eq!(point.collision(&ramp), None);
+    }
+
+    #[test]
+    fn test_polygon_too_many_vertices() {
+        let circle = (0..=MAX_POLYGON_VERTICES).map(|i| Vec2::from_angle(i as f32));
+        assert!(KinematicBody::polygon(circle, Vec2::ZERO, Vec2::ZERO).is_none());
+    }
+
+    #[test]
+    fn test_polygon_overlap() {
+        let ramp = KinematicBody::polygon(
+            vec![Vec2::ZERO, Vec2::new(2., 0.), Vec2::new(0., 2.)],
+            Vec2::ZERO,
+            Vec2::ZERO,
+        )
+        .unwrap();
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::new(1.4, 1.4), Vec2::ZERO);
+        assert!(ramp.overlaps(&aabb));
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::new(1.6, 1.6), Vec2::ZERO);
+        assert!(!ramp.overlaps(&aabb));
+        let circle = KinematicBody::circle(0.5, Vec2::new(1.3, 1.3), Vec2::ZERO);
+        assert!(circle.overlaps(&ramp));
+    }
+
+    #[test]
+    fn test_circle_polygon_collision() {
+        let circle = KinematicBody::circle(0.5, Vec2::ZERO, Vec2::new(2., 0.));
+        let square = KinematicBody::polygon(
+            vec![Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y],
+            Vec2::new(1., -0.5),
+            Vec2::ZERO,
+        )
+        .unwrap();
+        let actual = circle.collision(&square);
+        let expected = Collision {
+            motion: Vec2::new(0.5, 0.),
+            position: Vec2::new(1., 0.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_capsule_builders() {
+        let capsule = KinematicBody::new().capsule_size(Vec2::new(1., 2.));
+        assert_eq!(capsule.shape, Shape::Capsule(Vec2::new(1., 2.)));
+        assert_eq!(
+            capsule.shape.capsule_core(),
+            Some((Vec2::new(0., 0.5), 0.5))
+        );
+        assert_eq!(
+            Shape::Capsule(Vec2::new(3., 1.)).capsule_core(),
+            Some((Vec2::X, 0.5))
+        );
+    }
+
+    #[test]
+    fn test_capsule_aabb_collision() {
+        let capsule = KinematicBody::capsule(Vec2::new(1., 2.), Vec2::ZERO, Vec2::new(2., 0.));
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::new(2., 0.), Vec2::ZERO);
+        let actual = capsule.collision(&aabb);
+        let expected = Collision {
+            motion: Vec2::new(1., 0.),
+            position: Vec2::new(1.5, 0.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_capsule_tile_seam_non_collision() {
+        // Resting on one tile and gliding onto the next, flush tile must not catch the capsule
+        let capsule =
+            KinematicBody::capsule(Vec2::new(1., 2.), Vec2::new(0., 1.5), Vec2::new(1., 0.));
+        let tile = KinematicBody::aabb(Vec2::ONE, Vec2::new(1., 0.), Vec2::ZERO);
+        assert_eq!(capsule.collision(&tile), None);
+    }
+
+    #[test]
+    fn test_aabb_capsule_collision() {
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::new(0., -3.), Vec2::new(0., 2.));
+        let capsule = KinematicBody::capsule(Vec2::new(1., 2.), Vec2::ZERO, Vec2::ZERO);
+        let actual = aabb.collision(&capsule);
+        let expected = Collision {
+            motion: Vec2::new(0., 1.5),
+            position: Vec2::new(0., -1.),
+            normal: Some(-IVec2::Y),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_capsule_segment_collision() {
+        let capsule =
+            KinematicBody::capsule(Vec2::new(2., 1.), Vec2::new(0., 2.), Vec2::new(0., -2.));
+        let segment = KinematicBody::segment(Vec2::new(-2., 0.), Vec2::new(2., 0.));
+        let actual = capsule.collision(&segment);
+        let expected = Collision {
+            motion: Vec2::new(0., -1.5),
+            position: Vec2::ZERO,
+            normal: Some(IVec2::Y),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_point_capsule_collision() {
+        let point = KinematicBody::point(Vec2::new(-2., 0.), Vec2::new(4., 0.));
+        let capsule = KinematicBody::capsule(Vec2::new(1., 2.), Vec2::ZERO, Vec2::ZERO);
+        let actual = point.collision(&capsule);
+        let expected = Collision {
+            motion: Vec2::new(1.5, 0.),
+            position: Vec2::new(-0.5, 0.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_capsule_capsule_collision() {
+        let capsule_1 = KinematicBody::capsule(Vec2::new(1., 2.), Vec2::ZERO, Vec2::new(0., 4.));
+        let capsule_2 = KinematicBody::capsule(Vec2::new(1., 2.), Vec2::new(0., 4.), Vec2::ZERO);
+        let actual = capsule_1.collision(&capsule_2);
+        let expected = Collision {
+            motion: Vec2::new(0., 2.),
+            position: Vec2::new(0., 3.),
+            normal: Some(-IVec2::Y),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_capsule_overlap() {
+        let capsule = KinematicBody::capsule(Vec2::new(1., 2.), Vec2::ZERO, Vec2::ZERO);
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::new(0.9, 0.), Vec2::ZERO);
+        assert!(capsule.overlaps(&aabb));
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::new(0.9, 1.4), Vec2::ZERO);
+        assert!(!capsule.overlaps(&aabb));
+        let circle = KinematicBody::circle(0.5, Vec2::new(0., 1.4), Vec2::ZERO);
+        assert!(circle.overlaps(&capsule));
+        let segment = KinematicBody::segment(Vec2::new(0.4, -2.), Vec2::new(0.4, 2.));
+        assert!(segment.overlaps(&capsule));
     }
 
-    // #[test]
-    // fn test_point_aabb_collision() {
-    //     let point = KinematicBody::point(Vec2::ZERO, Vec2::ONE);
-    //     let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::new(0.5, 0.), Vec2::ZERO);
-    //     let actual = point.collision(&aabb);
-    //     let expected = Some(Collision {
-    //         motion: Vec2::splat(0.5),
-    //         position: Vec2::splat(0.5),
-    //         normal: Some(-IVec2::X),
-    //     });
-    //     assert_eq!(actual, expected);
-    // }
-
-    // #[test]
-    // fn test_point_aabb_non_collision() {
-    //     let point = KinematicBody::point(Vec2::ZERO, Vec2::ONE);
-    //     let aabb = KinematicBody::aabb(Vec2::new(1., 0.25), Vec2::new(0.5, 0.), Vec2::ZERO);
-    //     let actual = point.collision(&aabb);
-    //     let expected = None;
-    //     assert_eq!(actual, expected);
-    // }
+    #[test]
+    fn test_point_obb_collision() {
+        let point = KinematicBody::point(Vec2::new(-3., 0.5), Vec2::new(4., 0.));
+        let obb = KinematicBody::obb(Vec2::splat(2.), FRAC_PI_4, Vec2::ZERO, Vec2::ZERO);
+        let actual = point.collision(&obb).unwrap();
+        assert!(actual.motion.abs_diff_eq(Vec2::new(3.5 - SQRT_2, 0.), 1e-5));
+        assert!(actual
+            .position
+            .abs_diff_eq(Vec2::new(0.5 - SQRT_2, 0.5), 1e-5));
+        assert!(actual
+            .unit_normal
+            .unwrap()
+            .abs_diff_eq(Vec2::new(-1., 1.).normalize(), 1e-5));
+    }
+
+    #[test]
+    fn test_aabb_obb_collision() {
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::new(3., 0.));
+        let obb = KinematicBody::obb(
+            Vec2::splat(SQRT_2),
+            FRAC_PI_4,
+            Vec2::new(3., 0.),
+            Vec2::ZERO,
+        );
+        let actual = aabb.collision(&obb).unwrap();
+        assert!(actual.motion.abs_diff_eq(Vec2::new(1.5, 0.), 1e-5));
+        assert!(actual.position.abs_diff_eq(Vec2::new(2., 0.), 1e-5));
+        assert_eq!(actual.normal, Some(-IVec2::X));
+    }
+
+    #[test]
+    fn test_obb_obb_collision() {
+        let obb_1 = KinematicBody::obb(Vec2::splat(SQRT_2), FRAC_PI_4, Vec2::ZERO, Vec2::splat(2.));
+        let obb_2 = KinematicBody::obb(Vec2::splat(SQRT_2), FRAC_PI_4, Vec2::splat(2.), Vec2::ZERO);
+        let actual = obb_1.collision(&obb_2).unwrap();
+        assert!(actual.motion.abs_diff_eq(Vec2::ONE, 1e-5));
+        assert!(actual.position.abs_diff_eq(Vec2::splat(1.5), 1e-5));
+        assert!(actual
+            .unit_normal
+            .unwrap()
+            .abs_diff_eq(-Vec2::ONE.normalize(), 1e-5));
+    }
+
+    #[test]
+    fn test_circle_obb_collision() {
+        let circle = KinematicBody::circle(0.5, Vec2::ZERO, Vec2::new(3., 0.));
+        let obb = KinematicBody::obb(
+            Vec2::splat(SQRT_2),
+            FRAC_PI_4,
+            Vec2::new(3., 0.),
+            Vec2::ZERO,
+        );
+        let actual = circle.collision(&obb);
+        let expected = Collision {
+            motion: Vec2::new(1.5, 0.),
+            position: Vec2::new(2., 0.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+
+        // Swept the other way, the normal faces the obb
+        let obb = obb.position(Vec2::ZERO).motion(Vec2::new(3., 0.));
+        let circle = circle.position(Vec2::new(3., 0.)).motion(Vec2::ZERO);
+        let actual = obb.collision(&circle);
+        let expected = Collision {
+            motion: Vec2::new(1.5, 0.),
+            position: Vec2::new(2.5, 0.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_capsule_obb_collision() {
+        let obb = KinematicBody::obb(
+            Vec2::splat(SQRT_2),
+            FRAC_PI_4,
+            Vec2::new(3., 0.),
+            Vec2::ZERO,
+        );
+        let capsule = KinematicBody::capsule(Vec2::new(1., 2.), Vec2::ZERO, Vec2::new(3., 0.));
+        let actual = capsule.collision(&obb);
+        let expected = Collision {
+            motion: Vec2::new(1.5, 0.),
+            position: Vec2::new(2., 0.),
+            normal: Some(-IVec2::X),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+
+        // Landing on the top corner, the end of the capsule touches it
+        let capsule = capsule
+            .position(Vec2::new(3., 3.))
+            .motion(Vec2::new(0., -3.));
+        let actual = capsule.collision(&obb);
+        let expected = Collision {
+            motion: Vec2::new(0., -1.),
+            position: Vec2::new(3., 1.),
+            normal: Some(IVec2::Y),
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
+    }
+
+    #[test]
+    fn test_obb_overlap() {
+        let obb = KinematicBody::obb(Vec2::splat(SQRT_2), FRAC_PI_4, Vec2::ZERO, Vec2::ZERO);
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::new(1.2, 0.), Vec2::ZERO);
+        assert!(obb.overlaps(&aabb));
+        let aabb = KinematicBody::aabb(Vec2::ONE, Vec2::new(1.6, 0.), Vec2::ZERO);
+        assert!(!obb.overlaps(&aabb));
+        let circle = KinematicBody::circle(0.5, Vec2::splat(0.8), Vec2::ZERO);
+        assert!(circle.overlaps(&obb));
+        let circle = KinematicBody::circle(0.5, Vec2::splat(0.9), Vec2::ZERO);
+        assert!(!circle.overlaps(&obb));
+    }
 
     #[test]
     fn correct_aabb_aabb_collision_1() {
@@ -316,12 +2637,13 @@ mod tests {
             .motion(Vec2::new(5., 0.));
         let aabb_2 = KinematicBody::aabb(Vec2::splat(100.), Vec2::new(200., 0.), Vec2::ZERO);
         let actual = aabb_1.collision(&aabb_2);
-        let expected = Some(Collision {
+        let expected = Collision {
             motion: Vec2::new(0.01, 0.),
-            position: Vec2::new(150., 50.),
+            position: Vec2::new(150., 0.),
             normal: Some(-IVec2::X),
-        });
-        assert_eq!(actual, expected);
+            ..Default::default()
+        };
+        assert_collision(actual, expected);
     }
 
     #[test]
@@ -1,10 +1,19 @@
+pub mod broad_phase;
 pub mod kinematics;
 pub mod system;
 pub mod utils;
 
 pub mod prelude {
     pub use super::{
-        kinematics::{Collision, Flags, KinematicBody},
-        system::{CollisionEffect, CollisionPlugin, Kinematics},
+        broad_phase::{BroadPhase, BroadPhaseKind},
+        kinematics::{
+            Collision, CollisionResponse, FilterMode, Flags, KinematicBody, Penetration, Sensor,
+            Shape, Static, Vertices,
+        },
+        system::{
+            CollisionContacts, CollisionEffect, CollisionEnded, CollisionPersisting,
+            CollisionPlugin, CollisionStarted, Contacts, Kinematics, SensorEnter, SensorExit,
+            SensorStay, SpatialIndex,
+        },
     };
 }
@@ -1,20 +1,24 @@
 use bevy_app::{App, Plugin, Startup, Update};
 use bevy_color::Srgba;
 use bevy_ecs::{
+    component::Component,
     entity::Entity,
     event::Event,
+    query::{Added, Changed, Has, Or, Without},
+    removal_detection::RemovedComponents,
     resource::Resource,
     schedule::{IntoScheduleConfigs, SystemSet},
-    system::{Commands, In, IntoSystem, Query, Res},
+    system::{Commands, In, IntoSystem, Local, Query, Res, ResMut},
 };
-use bevy_gizmos::gizmos::Gizmos;
-use bevy_math::Vec2;
+use bevy_gizmos::{gizmos::Gizmos, primitives::dim2::GizmoPrimitive2d};
+use bevy_math::{primitives::Capsule2d, EulerRot, Isometry2d, Rot2, Vec2};
+use bevy_platform::collections::{HashMap, HashSet};
 use bevy_transform::components::Transform;
 use bevy_ui::{widget::Text, Node, Val};
 
 use crate::{
-    kinematics::{Collision, KinematicBody},
-    utils::chunk_map::ChunkMap,
+    broad_phase::{BroadPhase, BroadPhaseKind},
+    kinematics::{Collision, CollisionResponse, FilterMode, KinematicBody, Sensor, Shape, Static},
 };
 
 #[derive(SystemSet, Hash, Debug, Eq, PartialEq, Clone, Copy)]
@@ -29,12 +33,34 @@ pub struct CollisionConfig {
     pub chunk_size: f32,
     pub enable_debug: bool,
     pub enable_collision_effects: bool,
+    pub filter_mode: FilterMode,
+    pub max_slides: usize,
+    pub report_all_contacts: bool,
+    pub enable_mirrored_effects: bool,
+    pub depenetration_frames: u32,
+    pub skin_width: f32,
 }
 
 pub struct CollisionPlugin {
     pub chunk_size: f32,
     pub enable_debug: bool,
     pub enable_collision_effects: bool,
+    /// How body `mask` and `layer` flags are compared
+    pub filter_mode: FilterMode,
+    /// How many times a sliding body's remaining motion is re-tested after a hit
+    pub max_slides: usize,
+    /// Whether every body along each step of a sweep is reported, sorted by time of impact,
+    /// rather than only the first one hit
+    pub report_all_contacts: bool,
+    /// Whether struck bodies also receive a `CollisionEffect` pointing back at the mover
+    pub enable_mirrored_effects: bool,
+    /// Over how many frames bodies that already overlap are pushed apart, or 0 to leave them.
+    /// Either way movers are only kept from moving deeper into whatever they overlap.
+    pub depenetration_frames: u32,
+    /// How far short of surfaces movers stop, unless a body sets its own `skin_width`
+    pub skin_width: f32,
+    /// Which broad phase finds the bodies each mover is tested against
+    pub broad_phase: BroadPhaseKind,
 }
 
 impl Default for CollisionPlugin {
@@ -43,6 +69,13 @@ impl Default for CollisionPlugin {
             chunk_size: 1.,
             enable_debug: false,
             enable_collision_effects: false,
+            filter_mode: FilterMode::default(),
+            max_slides: 4,
+            report_all_contacts: false,
+            enable_mirrored_effects: false,
+            depenetration_frames: 0,
+            skin_width: 0.,
+            broad_phase: BroadPhaseKind::default(),
         }
     }
 }
@@ -53,11 +86,28 @@ impl Plugin for CollisionPlugin {
             chunk_size: self.chunk_size,
             enable_debug: self.enable_debug,
             enable_collision_effects: self.enable_collision_effects,
+            filter_mode: self.filter_mode,
+            max_slides: self.max_slides,
+            report_all_contacts: self.report_all_contacts,
+            enable_mirrored_effects: self.enable_mirrored_effects,
+            depenetration_frames: self.depenetration_frames,
+            skin_width: self.skin_width,
         })
+        .init_resource::<CollisionContacts>()
+        .insert_resource(SpatialIndex::with_broad_phase(
+            self.broad_phase,
+            self.chunk_size,
+        ))
         .add_systems(
             Update,
-            detect_collisions
-                .pipe(apply_motion)
+            (
+                sync_rotation,
+                update_spatial_index,
+                depenetrate,
+                detect_collisions.pipe(apply_motion),
+                detect_sensor_overlaps,
+            )
+                .chain()
                 .after(Kinematics::Motion)
                 .in_set(Kinematics::Collision),
         );
@@ -78,98 +128,585 @@ pub struct CollisionEffect {
     pub collision: Collision,
 }
 
-fn are_opposite(v1: Vec2, v2: Vec2) -> bool {
-    const TOLERANCE: f32 = 1e-6; // Small tolerance for floating-point precision
-    let dot_product = v1.normalize_or_zero().dot(v2.normalize_or_zero());
-    (dot_product + 1.0).abs() < TOLERANCE
+/// Triggered on a moving body the first frame it hits `other`
+#[derive(Event, Clone, Debug)]
+pub struct CollisionStarted {
+    pub other: Entity,
+    pub collision: Collision,
+}
+
+/// Triggered on a moving body every following frame it keeps hitting `other`
+#[derive(Event, Clone, Debug)]
+pub struct CollisionPersisting {
+    pub other: Entity,
+    pub collision: Collision,
+}
+
+/// Triggered on a moving body the first frame it no longer hits `other`
+#[derive(Event, Clone, Debug)]
+pub struct CollisionEnded {
+    pub other: Entity,
+}
+
+/// The contacts a moving body made during the last collision pass, in the order they were made.
+/// Only the first body hit at each step is listed unless `report_all_contacts` is set, in which
+/// case every body along the step's sweep follows it, sorted by time of impact.
+#[derive(Component, Clone, Debug, Default)]
+pub struct Contacts(pub Vec<(Entity, Collision)>);
+
+/// The pairs of moving and struck bodies that were in contact during the last collision pass
+#[derive(Resource, Default, Debug)]
+pub struct CollisionContacts {
+    pairs: HashSet<(Entity, Entity)>,
+}
+
+impl CollisionContacts {
+    /// Whether `entity` hit `other` during the last collision pass
+    pub fn contains(&self, entity: Entity, other: Entity) -> bool {
+        self.pairs.contains(&(entity, other))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, Entity)> + '_ {
+        self.pairs.iter().copied()
+    }
+}
+
+/// Triggered on a sensor when a body starts overlapping it
+#[derive(Event, Clone, Debug)]
+pub struct SensorEnter {
+    pub other: Entity,
+}
+
+/// Triggered on a sensor every frame a body keeps overlapping it
+#[derive(Event, Clone, Debug)]
+pub struct SensorStay {
+    pub other: Entity,
+}
+
+/// Triggered on a sensor when a body stops overlapping it
+#[derive(Event, Clone, Debug)]
+pub struct SensorExit {
+    pub other: Entity,
+}
+
+/// The broad phases every body is registered in, kept between frames so that bodies which do
+/// not change are only inserted once. Static bodies live in their own broad phase, which only
+/// changes when level geometry does, while moving bodies are re-registered as they move.
+#[derive(Resource)]
+pub struct SpatialIndex {
+    dynamic: Box<dyn BroadPhase>,
+    statics: Box<dyn BroadPhase>,
+}
+
+impl SpatialIndex {
+    pub fn new(chunk_size: f32) -> Self {
+        Self::with_broad_phase(BroadPhaseKind::default(), chunk_size)
+    }
+
+    pub fn with_broad_phase(kind: BroadPhaseKind, chunk_size: f32) -> Self {
+        Self::with_backends(kind.build(chunk_size), kind.build(chunk_size))
+    }
+
+    /// Uses custom broad phases for moving and static bodies
+    pub fn with_backends(dynamic: Box<dyn BroadPhase>, statics: Box<dyn BroadPhase>) -> Self {
+        Self { dynamic, statics }
+    }
+
+    /// Registers a moving body over its whole motion, so bodies larger or faster than a chunk
+    /// are still found from anywhere they could be hit. A body that was already registered is
+    /// moved.
+    pub fn insert(&mut self, entity: Entity, body: &KinematicBody) {
+        self.statics.remove(entity);
+        let (min, max) = body.swept_bounds();
+        self.dynamic.insert(entity, body, min, max);
+    }
+
+    /// Registers a static body where its shape is, ignoring its motion
+    pub fn insert_static(&mut self, entity: Entity, body: &KinematicBody) {
+        self.dynamic.remove(entity);
+        let (min, max) = body.bounds();
+        self.statics.insert(entity, body, min, max);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        if !self.dynamic.remove(entity) {
+            self.statics.remove(entity);
+        }
+    }
+
+    /// Whether `entity` is registered as a static body
+    pub fn is_static(&self, entity: Entity) -> bool {
+        self.statics.contains(entity)
+    }
+
+    /// Every registered body that may overlap the box spanning `min..max`, once each
+    pub fn candidates(&self, min: Vec2, max: Vec2) -> Vec<Entity> {
+        let mut candidates = Vec::new();
+        for broad_phase in [&self.dynamic, &self.statics] {
+            broad_phase.query(min, max, &mut |entity| candidates.push(entity));
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+
+    pub fn len(&self) -> usize {
+        self.dynamic.len() + self.statics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lets the broad phases catch up on a batch of inserts and removals before the next query
+    pub fn update(&mut self) {
+        self.dynamic.update();
+        self.statics.update();
+    }
+}
+
+/// Bodies that need to be registered again in the `SpatialIndex`
+type IndexChanged = Or<(Changed<KinematicBody>, Added<Static>)>;
+
+/// Registers added and changed bodies in the `SpatialIndex` and drops removed ones. Bodies
+/// gaining or losing `Static` move between its static and dynamic maps.
+pub fn update_spatial_index(
+    mut index: ResMut<SpatialIndex>,
+    query: Query<(Entity, &KinematicBody, Has<Static>), IndexChanged>,
+    dynamic: Query<&KinematicBody, Without<Static>>,
+    mut removed: RemovedComponents<KinematicBody>,
+    mut removed_static: RemovedComponents<Static>,
+) {
+    for entity in removed.read() {
+        index.remove(entity);
+    }
+    for entity in removed_static.read() {
+        if let Ok(body) = dynamic.get(entity) {
+            index.insert(entity, body);
+        }
+    }
+    for (entity, body, is_static) in query.iter() {
+        if is_static {
+            index.insert_static(entity, body);
+        } else {
+            index.insert(entity, body);
+        }
+    }
+    index.update();
+}
+
+/// Finds every collision of `body` against the bodies sharing a chunk with its motion, sorted by
+/// time of impact
+fn sweep_collisions<'a>(
+    entity: Entity,
+    body: &KinematicBody,
+    ignore: &[Entity],
+    index: &SpatialIndex,
+    bodies: impl Fn(Entity) -> Option<&'a KinematicBody>,
+    config: &CollisionConfig,
+    gizmos: &mut Gizmos,
+) -> Vec<(f32, Entity, Collision)> {
+    let mut collisions = Vec::new();
+    let (min, max) = body.swept_bounds();
+    for e2 in index.candidates(min, max) {
+        if entity == e2 || ignore.contains(&e2) {
+            continue;
+        }
+        let Some(k2) = bodies(e2) else {
+            continue;
+        };
+        if !body.can_collide(k2, config.filter_mode) {
+            continue;
+        }
+        if let Some(collision) = body.collision(k2) {
+            // Bodies that already overlap are handled by `block_overlaps` before sweeping
+            if collision.toi < 0. {
+                continue;
+            }
+            // println!(
+            //     "cp: {:?}, cm: {:?}, k1: {:?}, k2: {:?}, m: {:?}",
+            //     collision.position, collision.motion, body.position, k2.position, body.motion
+            // );
+            if config.enable_debug {
+                gizmos.circle_2d(collision.position, config.chunk_size / 20., Srgba::BLUE);
+            }
+            if collision.toi < 1. {
+                collisions.push((collision.toi, e2, collision));
+            }
+        }
+    }
+    collisions.sort_by(|(toi_1, ..), (toi_2, ..)| toi_1.total_cmp(toi_2));
+    collisions
 }
 
+/// Removes the part of `body`'s motion going deeper into any body it already overlaps, returning
+/// the contacts with those bodies. Motion out of or along them is kept, so that overlapping
+/// bodies can always move apart.
+fn block_overlaps<'a>(
+    entity: Entity,
+    body: &mut KinematicBody,
+    index: &SpatialIndex,
+    bodies: impl Fn(Entity) -> Option<&'a KinematicBody>,
+    config: &CollisionConfig,
+) -> Vec<(Entity, Collision)> {
+    let mut contacts = Vec::new();
+    let (min, max) = body.bounds();
+    for e2 in index.candidates(min, max) {
+        let Some(k2) = bodies(e2).filter(|_| e2 != entity) else {
+            continue;
+        };
+        if !body.can_collide(k2, config.filter_mode) {
+            continue;
+        }
+        let Some(penetration) = body.penetration(k2) else {
+            continue;
+        };
+        let inward = body.motion.dot(penetration.normal);
+        if inward < 0. {
+            body.motion -= inward * penetration.normal;
+            contacts.push((e2, penetration.contact(body.position)));
+        }
+    }
+    contacts
+}
+
+/// A body swept by `detect_collisions`, with how it responds to hits and where its contacts go
+type Mover = (
+    Entity,
+    &'static KinematicBody,
+    Option<&'static CollisionResponse>,
+    Has<Sensor>,
+    Option<&'static mut Contacts>,
+);
+
 pub fn detect_collisions(
     mut commands: Commands,
-    query: Query<(Entity, &KinematicBody)>,
+    mut movers: Query<Mover, Without<Static>>,
+    bodies: Query<&KinematicBody, Without<Sensor>>,
     config: Res<CollisionConfig>,
+    mut contacts: ResMut<CollisionContacts>,
+    index: Res<SpatialIndex>,
     mut gizmos: Gizmos,
-) -> Vec<(Entity, Vec2)> {
-    let mut chunks = ChunkMap::new(0, config.chunk_size);
+) -> Vec<(Entity, Vec2, Vec2)> {
+    let mut solutions = Vec::new();
+    let mut pairs = HashSet::new();
 
-    for (entity, body) in query.iter() {
-        chunks.insert(body.position, (entity, body));
-    }
+    // Sensors neither block nor get blocked
+    let bodies = |entity| bodies.get(entity).ok();
 
-    let mut solutions = Vec::new();
+    for (e1, k1, response, is_sensor, mut frame_contacts) in movers.iter_mut() {
+        if let Some(frame_contacts) = frame_contacts.as_mut() {
+            frame_contacts.0.clear();
+        }
+        if k1.motion == Vec2::ZERO {
+            continue;
+        }
+        if is_sensor {
+            solutions.push((e1, k1.motion, Vec2::ZERO));
+            continue;
+        }
+        // Segments have no swept tests against anything, so rather than passing through every
+        // body they are left where they are
+        if matches!(k1.shape, Shape::Segment(..)) {
+            continue;
+        }
+        let response = response.copied().unwrap_or_default();
+        // Bodies passing through have no surface to keep away from
+        let skin_width = match response {
+            CollisionResponse::PassThrough => 0.,
+            _ => k1.skin_width.unwrap_or(config.skin_width),
+        };
 
-    for (id1, values) in chunks.map.iter() {
-        for (e1, k1) in values.iter() {
-            let mut min_motion_1 = k1.motion;
-            if min_motion_1 == Vec2::ZERO {
-                continue;
-            }
-            let mut min_distance_1 = min_motion_1.length();
-            let mut min_collision = None;
-            let mut min_entity = None;
-            chunks.iter_neighbors(*id1, |_id2, (e2, k2)| {
-                if e1 == e2 {
-                    return;
-                }
-                if let Some(collision) = k1.collision(k2) {
-                    // println!(
-                    //     "cp: {:?}, cm: {:?}, k1: {:?}, k2: {:?}, m: {:?}",
-                    //     collision.position, collision.motion, k1.position, k2.position, k1.motion
-                    // );
-                    if config.enable_debug {
-                        gizmos.circle_2d(collision.position, config.chunk_size / 20., Srgba::BLUE);
-                    }
-                    let motion_1 = collision.motion;
-                    let distance_1 = motion_1.length();
-                    if distance_1 < min_distance_1 || are_opposite(collision.motion, k1.motion) {
-                        // println!(
-                        //     "{:?}, {:?}, {:?}, {:?}",
-                        //     distance_1, min_distance_1, motion_1, min_motion_1
-                        // );
-                        min_distance_1 = distance_1;
-                        min_motion_1 = motion_1;
-                        min_collision = Some(collision);
-                        min_entity = Some(*e2);
-                    }
+        let mut body = (*k1).clone();
+        let mut reported = Vec::new();
+        if response != CollisionResponse::PassThrough {
+            reported = block_overlaps(e1, &mut body, &index, bodies, &config);
+        }
+
+        // Resolve the motion in steps, re-testing whatever motion the response keeps after
+        // each hit
+        let mut motion = Vec2::ZERO;
+        let mut next_motion = k1.motion;
+        let mut passed = Vec::new();
+        let mut steps = 0;
+        while body.motion != Vec2::ZERO {
+            let collisions =
+                sweep_collisions(e1, &body, &passed, &index, bodies, &config, &mut gizmos);
+            let Some(&(_, _, collision)) = collisions.first() else {
+                motion += body.motion;
+                break;
+            };
+            let collision = collision.with_skin(body.motion, skin_width);
+
+            // Report every body along the sweep, such as both tiles of a corner, or only the
+            // one that stops it
+            let count = if config.report_all_contacts {
+                collisions.len()
+            } else {
+                1
+            };
+            for &(_, e2, contact) in &collisions[..count] {
+                if response == CollisionResponse::PassThrough {
+                    passed.push(e2);
                 }
-            });
-            solutions.push((*e1, min_motion_1));
+                // Report the motion that is actually applied
+                reported.push((e2, contact.with_skin(body.motion, skin_width)));
+            }
+
+            motion += collision.motion;
+            body.position += collision.motion;
+            let remaining = body.motion - collision.motion;
+            if let CollisionResponse::Bounce { .. } = response {
+                next_motion = response
+                    .resolve(next_motion, collision.unit_normal)
+                    .unwrap_or(Vec2::ZERO);
+            }
+            // Passing through is bounded by the bodies around the mover rather than by
+            // `max_slides`, since every body is only passed once
+            steps += 1;
+            let can_continue =
+                response == CollisionResponse::PassThrough || steps <= config.max_slides;
+            match response.resolve(remaining, collision.unit_normal) {
+                Some(next) if next != Vec2::ZERO && can_continue => body.motion = next,
+                _ => break,
+            }
+        }
+        if !matches!(response, CollisionResponse::Bounce { .. }) {
+            next_motion = Vec2::ZERO;
+        }
+        solutions.push((e1, motion, next_motion));
+
+        for (e2, contact) in reported {
+            if let Some(frame_contacts) = frame_contacts.as_mut() {
+                frame_contacts.0.push((e2, contact));
+            }
+            let is_new_pair = pairs.insert((e1, e2));
             if config.enable_collision_effects {
-                if let Some(collision) = min_collision {
+                commands.trigger_targets(
+                    CollisionEffect {
+                        other: e2,
+                        collision: contact,
+                    },
+                    e1,
+                );
+                if config.enable_mirrored_effects {
                     commands.trigger_targets(
                         CollisionEffect {
-                            other: min_entity.unwrap(),
-                            collision,
+                            other: e1,
+                            collision: contact.mirrored(),
                         },
-                        *e1,
+                        e2,
                     );
                 }
             }
+            // Contacts are tracked whether or not effects are enabled
+            if !is_new_pair {
+                continue;
+            }
+            if contacts.contains(e1, e2) {
+                commands.trigger_targets(
+                    CollisionPersisting {
+                        other: e2,
+                        collision: contact,
+                    },
+                    e1,
+                );
+            } else {
+                commands.trigger_targets(
+                    CollisionStarted {
+                        other: e2,
+                        collision: contact,
+                    },
+                    e1,
+                );
+            }
         }
     }
 
+    for (e1, e2) in contacts.pairs.difference(&pairs).copied() {
+        commands.trigger_targets(CollisionEnded { other: e2 }, e1);
+    }
+    contacts.pairs = pairs;
+
     solutions
 }
 
 pub fn apply_motion(
-    In(solutions): In<Vec<(Entity, Vec2)>>,
+    In(solutions): In<Vec<(Entity, Vec2, Vec2)>>,
     mut query: Query<(&mut Transform, &mut KinematicBody)>,
+    mut index: ResMut<SpatialIndex>,
 ) {
-    for (e, m) in solutions {
+    for (e, m, next_m) in solutions {
         let Ok((mut t, mut kb)) = query.get_mut(e) else {
             continue;
         };
         // println!("{:?}", m);
         t.translation += m.extend(0.);
         kb.position = t.translation.truncate();
-        kb.motion = Vec2::ZERO;
+        kb.motion = next_m;
+        // Keep the index current for the sensor pass later this frame
+        index.insert(e, &kb);
+    }
+    index.update();
+}
+
+/// Bodies that may have been rotated or just switched to an oriented box
+type RotationChanged = Or<(Changed<Transform>, Changed<KinematicBody>)>;
+
+/// Copies the rotation of each oriented box's `Transform` into its shape
+pub fn sync_rotation(mut query: Query<(&Transform, &mut KinematicBody), RotationChanged>) {
+    for (transform, mut body) in query.iter_mut() {
+        let (angle, _, _) = transform.rotation.to_euler(EulerRot::ZYX);
+        let Shape::Obb(size, body_angle) = body.shape else {
+            continue;
+        };
+        if body_angle != angle {
+            body.shape = Shape::Obb(size, angle);
+        }
+    }
+}
+
+/// Pushes overlapping bodies apart, spreading each separation over `depenetration_frames` frames.
+/// Static bodies and segments stay put and both sides of any other pair move half the way.
+pub fn depenetrate(
+    mut query: Query<(Entity, &mut Transform, &mut KinematicBody, Has<Static>), Without<Sensor>>,
+    config: Res<CollisionConfig>,
+    mut index: ResMut<SpatialIndex>,
+    mut frames_left: Local<HashMap<(Entity, Entity), u32>>,
+) {
+    if config.depenetration_frames == 0 {
+        return;
+    }
+
+    let mut overlapping = HashMap::new();
+    let mut pushes = HashMap::<Entity, Vec2>::new();
+    for (e1, _, k1, _) in query.iter().filter(|(.., is_static)| !is_static) {
+        let (min, max) = k1.bounds();
+        for e2 in index.candidates(min, max) {
+            // Visit each pair once, from its lower moving entity
+            if e1 == e2 || (e1 > e2 && !index.is_static(e2)) {
+                continue;
+            }
+            let Ok((_, _, k2, is_static)) = query.get(e2) else {
+                continue;
+            };
+            if !(k1.can_collide(k2, config.filter_mode) || k2.can_collide(k1, config.filter_mode)) {
+                continue;
+            }
+            let Some(penetration) = k1.penetration(k2) else {
+                continue;
+            };
+
+            // Move by an even share of what is left, so the pair is apart on the last frame
+            let left = frames_left
+                .get(&(e1, e2))
+                .copied()
+                .unwrap_or(config.depenetration_frames)
+                .max(1);
+            overlapping.insert((e1, e2), left - 1);
+            let push = penetration.mtv() / left as f32;
+            let fixed = is_static || matches!(k2.shape, Shape::Segment(..));
+            let (share, other_share) = match (k1.shape, fixed) {
+                (Shape::Segment(..), true) => continue,
+                (_, true) => (1., 0.),
+                (Shape::Segment(..), false) => (0., 1.),
+                _ => (0.5, 0.5),
+            };
+            if share > 0. {
+                *pushes.entry(e1).or_default() += share * push;
+            }
+            if other_share > 0. {
+                *pushes.entry(e2).or_default() -= other_share * push;
+            }
+        }
+    }
+    *frames_left = overlapping;
+
+    for (entity, push) in pushes {
+        let Ok((_, mut transform, mut body, _)) = query.get_mut(entity) else {
+            continue;
+        };
+        transform.translation += push.extend(0.);
+        body.position = transform.translation.truncate();
+        index.insert(entity, &body);
+    }
+    index.update();
+}
+
+pub fn detect_sensor_overlaps(
+    mut commands: Commands,
+    query: Query<&KinematicBody>,
+    mut sensors: Query<(Entity, &KinematicBody, &mut Sensor)>,
+    config: Res<CollisionConfig>,
+    index: Res<SpatialIndex>,
+) {
+    for (e1, k1, mut sensor) in sensors.iter_mut() {
+        let (min, max) = k1.bounds();
+        let overlaps: HashSet<_> = index
+            .candidates(min, max)
+            .into_iter()
+            .filter(|e2| {
+                *e2 != e1
+                    && query
+                        .get(*e2)
+                        .is_ok_and(|k2| k1.can_collide(k2, config.filter_mode) && k1.overlaps(k2))
+            })
+            .collect();
+
+        for other in overlaps.iter().copied() {
+            if sensor.overlaps.contains(&other) {
+                commands.trigger_targets(SensorStay { other }, e1);
+            } else {
+                commands.trigger_targets(SensorEnter { other }, e1);
+            }
+        }
+        for other in sensor.overlaps.difference(&overlaps).copied() {
+            commands.trigger_targets(SensorExit { other }, e1);
+        }
+        sensor.overlaps = overlaps;
     }
 }
 
 pub fn draw_debug_rects(query: Query<&KinematicBody>, mut gizmos: Gizmos) {
     for k in query.iter() {
-        if let Some(size) = k.size {
-            gizmos.rect_2d(k.position, size, Srgba::RED);
+        match k.shape {
+            Shape::Aabb(size) => {
+                gizmos.rect_2d(k.position, size, Srgba::RED);
+            }
+            Shape::Circle(radius) => {
+                gizmos.circle_2d(k.position, radius, Srgba::RED);
+            }
+            Shape::Segment(start, end) => {
+                gizmos.line_2d(k.position + start, k.position + end, Srgba::RED);
+            }
+            Shape::Capsule(_) => {
+                let Some((core, radius)) = k.shape.capsule_core() else {
+                    continue;
+                };
+                // The gizmo capsule is upright, so lay it down when the core is horizontal
+                let rotation = if core.x > 0. {
+                    Rot2::degrees(90.)
+                } else {
+                    Rot2::IDENTITY
+                };
+                gizmos.primitive_2d(
+                    &Capsule2d::new(radius, 2. * core.length()),
+                    Isometry2d::new(k.position, rotation),
+                    Srgba::RED,
+                );
+            }
+            Shape::Obb(size, angle) => {
+                gizmos.rect_2d(
+                    Isometry2d::new(k.position, Rot2::radians(angle)),
+                    size,
+                    Srgba::RED,
+                );
+            }
+            Shape::Polygon(vertices) => {
+                let closed = vertices.iter().chain(vertices.first());
+                gizmos.linestrip_2d(closed.map(|vertex| k.position + *vertex), Srgba::RED);
+            }
+            _ => {}
         }
     }
 }
@@ -191,3 +728,412 @@ pub fn draw_screen_diagnostics(mut query: Query<&mut Text>, query_k: Query<&Kine
         text.0 = format!("Colliders: {}", query_k.iter().len());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::MinimalPlugins;
+    use bevy_ecs::observer::Trigger;
+    use bevy_gizmos::{
+        config::{DefaultGizmoConfigGroup, GizmoConfig, GizmoConfigStore},
+        gizmos::GizmoStorage,
+    };
+
+    /// Every event triggered during a test, as the target, the event name and the other entity
+    #[derive(Resource, Default)]
+    struct Triggered(Vec<(Entity, &'static str, Entity)>);
+
+    fn app(plugin: CollisionPlugin) -> App {
+        let mut gizmos = GizmoConfigStore::default();
+        gizmos.insert(GizmoConfig::default(), DefaultGizmoConfigGroup);
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, plugin))
+            .insert_resource(gizmos)
+            .init_resource::<GizmoStorage<DefaultGizmoConfigGroup, ()>>()
+            .init_resource::<Triggered>();
+        app
+    }
+
+    fn record<E: Event>(app: &mut App, name: &'static str, other: fn(&E) -> Entity) {
+        app.add_observer(
+            move |trigger: Trigger<E>, mut triggered: ResMut<Triggered>| {
+                triggered
+                    .0
+                    .push((trigger.target(), name, other(trigger.event())));
+            },
+        );
+    }
+
+    /// Takes the events triggered since the last call
+    fn triggered(app: &mut App) -> Vec<(Entity, &'static str, Entity)> {
+        std::mem::take(&mut app.world_mut().resource_mut::<Triggered>().0)
+    }
+
+    fn spawn(app: &mut App, body: KinematicBody) -> Entity {
+        let transform = Transform::from_translation(body.position.extend(0.));
+        app.world_mut().spawn((transform, body)).id()
+    }
+
+    fn set_motion(app: &mut App, entity: Entity, motion: Vec2) {
+        let mut entity = app.world_mut().entity_mut(entity);
+        entity.get_mut::<KinematicBody>().unwrap().motion = motion;
+    }
+
+    fn position(app: &App, entity: Entity) -> Vec2 {
+        let transform = app.world().entity(entity).get::<Transform>().unwrap();
+        transform.translation.truncate()
+    }
+
+    #[test]
+    fn test_collision_started_persisting_ended() {
+        let mut app = app(CollisionPlugin::default());
+        record(&mut app, "started", |event: &CollisionStarted| event.other);
+        record(&mut app, "persisting", |event: &CollisionPersisting| {
+            event.other
+        });
+        record(&mut app, "ended", |event: &CollisionEnded| event.other);
+        let mover = spawn(
+            &mut app,
+            KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::new(2., 0.)),
+        );
+        let wall = spawn(
+            &mut app,
+            KinematicBody::aabb(Vec2::ONE, Vec2::new(2., 0.), Vec2::ZERO),
+        );
+
+        app.update();
+        assert_eq!(position(&app, mover), Vec2::new(1., 0.));
+        assert_eq!(triggered(&mut app), vec![(mover, "started", wall)]);
+        assert!(app
+            .world()
+            .resource::<CollisionContacts>()
+            .contains(mover, wall));
+
+        // Pushing against the wall keeps the contact without moving
+        set_motion(&mut app, mover, Vec2::new(2., 0.));
+        app.update();
+        assert_eq!(position(&app, mover), Vec2::new(1., 0.));
+        assert_eq!(triggered(&mut app), vec![(mover, "persisting", wall)]);
+
+        app.update();
+        assert_eq!(triggered(&mut app), vec![(mover, "ended", wall)]);
+        assert_eq!(
+            app.world().resource::<CollisionContacts>().iter().count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_collision_effects() {
+        let mut app = app(CollisionPlugin {
+            enable_collision_effects: true,
+            enable_mirrored_effects: true,
+            ..Default::default()
+        });
+        record(&mut app, "effect", |event: &CollisionEffect| event.other);
+        let mover = spawn(
+            &mut app,
+            KinematicBody::point(Vec2::ZERO, Vec2::new(2., 0.)),
+        );
+        let wall = spawn(
+            &mut app,
+            KinematicBody::aabb(Vec2::ONE, Vec2::new(2., 0.), Vec2::ZERO),
+        );
+
+        app.update();
+        assert_eq!(
+            triggered(&mut app),
+            vec![(mover, "effect", wall), (wall, "effect", mover)]
+        );
+    }
+
+    #[test]
+    fn test_report_all_contacts() {
+        for report_all_contacts in [false, true] {
+            let mut app = app(CollisionPlugin {
+                report_all_contacts,
+                enable_collision_effects: true,
+                ..Default::default()
+            });
+            record(&mut app, "effect", |event: &CollisionEffect| event.other);
+            let mover = spawn(
+                &mut app,
+                KinematicBody::point(Vec2::ZERO, Vec2::new(5., 0.)),
+            );
+            app.world_mut()
+                .entity_mut(mover)
+                .insert(Contacts::default());
+            let far = spawn(
+                &mut app,
+                KinematicBody::aabb(Vec2::ONE, Vec2::new(4., 0.), Vec2::ZERO),
+            );
+            let near = spawn(
+                &mut app,
+                KinematicBody::aabb(Vec2::ONE, Vec2::new(2., 0.), Vec2::ZERO),
+            );
+
+            app.update();
+            assert_eq!(position(&app, mover), Vec2::new(1.5, 0.));
+            let contacts = app.world().entity(mover).get::<Contacts>().unwrap();
+            let hits: Vec<_> = contacts.0.iter().map(|(e, c)| (*e, c.toi)).collect();
+            if report_all_contacts {
+                // Sorted by time of impact, including the body beyond the one that stops it
+                assert_eq!(hits, vec![(near, 0.3), (far, 0.7)]);
+                assert_eq!(
+                    triggered(&mut app),
+                    vec![(mover, "effect", near), (mover, "effect", far)]
+                );
+            } else {
+                assert_eq!(hits, vec![(near, 0.3)]);
+                assert_eq!(triggered(&mut app), vec![(mover, "effect", near)]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stop_and_slide() {
+        for (response, expected) in [
+            (CollisionResponse::Stop, Vec2::new(1., 0.)),
+            (CollisionResponse::Slide, Vec2::new(2., 0.)),
+        ] {
+            let mut app = app(CollisionPlugin::default());
+            let mover = spawn(
+                &mut app,
+                KinematicBody::point(Vec2::new(0., 1.), Vec2::new(2., -2.)),
+            );
+            app.world_mut().entity_mut(mover).insert(response);
+            spawn(
+                &mut app,
+                KinematicBody::aabb(Vec2::new(8., 1.), Vec2::new(0., -0.5), Vec2::ZERO),
+            );
+
+            app.update();
+            assert_eq!(position(&app, mover), expected, "{response:?}");
+        }
+    }
+
+    #[test]
+    fn test_slide_into_corner() {
+        let mut app = app(CollisionPlugin::default());
+        let mover = spawn(
+            &mut app,
+            KinematicBody::point(Vec2::new(0., 1.), Vec2::new(2., -2.)),
+        );
+        app.world_mut()
+            .entity_mut(mover)
+            .insert(CollisionResponse::Slide);
+        spawn(
+            &mut app,
+            KinematicBody::aabb(Vec2::new(8., 1.), Vec2::new(0., -0.5), Vec2::ZERO),
+        );
+        spawn(
+            &mut app,
+            KinematicBody::aabb(Vec2::new(1., 4.), Vec2::new(2., 0.), Vec2::ZERO),
+        );
+
+        // The slide along the floor stops at the wall rather than passing through it
+        app.update();
+        assert_eq!(position(&app, mover), Vec2::new(1.5, 0.));
+    }
+
+    #[test]
+    fn test_bounce() {
+        let mut app = app(CollisionPlugin::default());
+        let mover = spawn(
+            &mut app,
+            KinematicBody::point(Vec2::ZERO, Vec2::new(2., 0.)),
+        );
+        app.world_mut()
+            .entity_mut(mover)
+            .insert(CollisionResponse::Bounce { restitution: 0.5 });
+        spawn(
+            &mut app,
+            KinematicBody::aabb(Vec2::new(1., 4.), Vec2::new(1.5, 0.), Vec2::ZERO),
+        );
+
+        // Half of the motion left at the wall comes back, and the next frame's motion too
+        app.update();
+        assert_eq!(position(&app, mover), Vec2::new(0.5, 0.));
+        let body = app.world().entity(mover).get::<KinematicBody>().unwrap();
+        assert_eq!(body.motion, -Vec2::X);
+    }
+
+    #[test]
+    fn test_pass_through() {
+        let mut app = app(CollisionPlugin {
+            enable_collision_effects: true,
+            max_slides: 1,
+            ..Default::default()
+        });
+        record(&mut app, "effect", |event: &CollisionEffect| event.other);
+        let mover = spawn(
+            &mut app,
+            KinematicBody::point(Vec2::ZERO, Vec2::new(10., 0.)),
+        );
+        app.world_mut()
+            .entity_mut(mover)
+            .insert(CollisionResponse::PassThrough);
+        let walls: Vec<_> = (1..=5)
+            .map(|i| {
+                let wall =
+                    KinematicBody::aabb(Vec2::new(0.5, 4.), Vec2::new(i as f32, 0.), Vec2::ZERO);
+                spawn(&mut app, wall)
+            })
+            .collect();
+
+        // Every wall is passed once, however many more there are than `max_slides`
+        app.update();
+        assert_eq!(position(&app, mover), Vec2::new(10., 0.));
+        let passed: Vec<_> = triggered(&mut app)
+            .into_iter()
+            .map(|(_, _, other)| other)
+            .collect();
+        assert_eq!(passed, walls);
+    }
+
+    #[test]
+    fn test_sensor_enter_stay_exit() {
+        let mut app = app(CollisionPlugin::default());
+        record(&mut app, "enter", |event: &SensorEnter| event.other);
+        record(&mut app, "stay", |event: &SensorStay| event.other);
+        record(&mut app, "exit", |event: &SensorExit| event.other);
+        let sensor = spawn(
+            &mut app,
+            KinematicBody::aabb(Vec2::splat(2.), Vec2::ZERO, Vec2::ZERO),
+        );
+        app.world_mut().entity_mut(sensor).insert(Sensor::new());
+        let mover = spawn(
+            &mut app,
+            KinematicBody::point(Vec2::new(-3., 0.), Vec2::new(2.5, 0.)),
+        );
+
+        // The sensor does not block the body entering it
+        app.update();
+        assert_eq!(position(&app, mover), Vec2::new(-0.5, 0.));
+        assert_eq!(triggered(&mut app), vec![(sensor, "enter", mover)]);
+        let overlaps = app
+            .world()
+            .entity(sensor)
+            .get::<Sensor>()
+            .unwrap()
+            .overlaps();
+        assert_eq!(overlaps.collect::<Vec<_>>(), vec![mover]);
+
+        app.update();
+        assert_eq!(triggered(&mut app), vec![(sensor, "stay", mover)]);
+
+        set_motion(&mut app, mover, Vec2::new(3., 0.));
+        app.update();
+        assert_eq!(position(&app, mover), Vec2::new(2.5, 0.));
+        assert_eq!(triggered(&mut app), vec![(sensor, "exit", mover)]);
+    }
+
+    #[test]
+    fn test_move_out_of_overlap() {
+        let mut app = app(CollisionPlugin::default());
+        record(&mut app, "started", |event: &CollisionStarted| event.other);
+        let mover = spawn(
+            &mut app,
+            KinematicBody::aabb(Vec2::splat(2.), Vec2::ZERO, Vec2::new(1., 0.5)),
+        );
+        let wall = spawn(
+            &mut app,
+            KinematicBody::aabb(Vec2::splat(2.), Vec2::new(1., 0.), Vec2::ZERO),
+        );
+        app.world_mut().entity_mut(wall).insert(Static);
+
+        // Only the motion deeper into the wall is removed
+        app.update();
+        assert_eq!(position(&app, mover), Vec2::new(0., 0.5));
+        assert_eq!(triggered(&mut app), vec![(mover, "started", wall)]);
+
+        // Moving away from the body it overlaps is never blocked
+        set_motion(&mut app, mover, -Vec2::X);
+        app.update();
+        assert_eq!(position(&app, mover), Vec2::new(-1., 0.5));
+    }
+
+    #[test]
+    fn test_depenetrate() {
+        let mut app = app(CollisionPlugin {
+            depenetration_frames: 2,
+            ..Default::default()
+        });
+        let mover = spawn(
+            &mut app,
+            KinematicBody::aabb(Vec2::splat(2.), Vec2::ZERO, Vec2::X),
+        );
+        let wall = spawn(
+            &mut app,
+            KinematicBody::aabb(Vec2::splat(2.), Vec2::new(1., 0.), Vec2::ZERO),
+        );
+        app.world_mut().entity_mut(wall).insert(Static);
+
+        // Half the overlap is undone each frame, even while the mover keeps pushing into it
+        app.update();
+        assert_eq!(position(&app, mover), Vec2::new(-0.5, 0.));
+        set_motion(&mut app, mover, Vec2::X);
+        app.update();
+        assert_eq!(position(&app, mover), -Vec2::X);
+        set_motion(&mut app, mover, Vec2::X);
+        app.update();
+        assert_eq!(position(&app, mover), -Vec2::X);
+        assert_eq!(position(&app, wall), Vec2::X);
+    }
+
+    #[test]
+    fn test_spatial_index_follows_bodies() {
+        let mut app = app(CollisionPlugin::default());
+        let mover = spawn(
+            &mut app,
+            KinematicBody::point(Vec2::ZERO, Vec2::new(5., 0.)),
+        );
+        let other = spawn(
+            &mut app,
+            KinematicBody::point(Vec2::new(0., 5.), Vec2::ZERO),
+        );
+        let around = |point: Vec2| (point - 0.5, point + 0.5);
+
+        // The mover is found where it ended up, not where it started
+        app.update();
+        let index = app.world().resource::<SpatialIndex>();
+        assert_eq!(index.len(), 2);
+        let (min, max) = around(Vec2::new(5., 0.));
+        assert_eq!(index.candidates(min, max), vec![mover]);
+        let (min, max) = around(Vec2::ZERO);
+        assert_eq!(index.candidates(min, max), vec![]);
+
+        app.world_mut().despawn(other);
+        app.update();
+        let index = app.world().resource::<SpatialIndex>();
+        assert_eq!(index.len(), 1);
+        let (min, max) = around(Vec2::new(0., 5.));
+        assert_eq!(index.candidates(min, max), vec![]);
+    }
+
+    #[test]
+    fn test_static_index() {
+        let mut app = app(CollisionPlugin::default());
+        let wall = spawn(
+            &mut app,
+            KinematicBody::aabb(Vec2::ONE, Vec2::ZERO, Vec2::new(5., 0.)),
+        );
+        app.world_mut().entity_mut(wall).insert(Static);
+
+        // Static bodies are indexed apart and never moved, whatever their motion
+        app.update();
+        assert!(app.world().resource::<SpatialIndex>().is_static(wall));
+        assert_eq!(position(&app, wall), Vec2::ZERO);
+
+        app.world_mut().entity_mut(wall).remove::<Static>();
+        app.update();
+        assert!(!app.world().resource::<SpatialIndex>().is_static(wall));
+        assert_eq!(app.world().resource::<SpatialIndex>().len(), 1);
+        assert_eq!(position(&app, wall), Vec2::new(5., 0.));
+
+        app.world_mut().entity_mut(wall).insert(Static);
+        app.update();
+        assert!(app.world().resource::<SpatialIndex>().is_static(wall));
+        assert_eq!(app.world().resource::<SpatialIndex>().len(), 1);
+    }
+}
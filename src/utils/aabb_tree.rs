@@ -0,0 +1,487 @@
+use bevy_math::Vec2;
+
+/// Handle to a value stored in an `AabbTree`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ProxyId(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Aabb {
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Self { min, max }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
+    pub fn contains(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.min).all() && other.max.cmple(self.max).all()
+    }
+
+    pub fn perimeter(&self) -> f32 {
+        let size = self.max - self.min;
+        2. * (size.x + size.y)
+    }
+
+    /// Grows the box by `margin` on every side and by `displacement` towards where it is heading
+    pub fn fattened(&self, margin: f32, displacement: Vec2) -> Aabb {
+        let min = self.min - margin;
+        let max = self.max + margin;
+        Aabb::new(
+            min + displacement.min(Vec2::ZERO),
+            max + displacement.max(Vec2::ZERO),
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Node<T> {
+    aabb: Aabb,
+    /// 0 for leaves, otherwise one more than the taller child
+    height: u32,
+    parent: Option<usize>,
+    /// Both children of a branch, or `None` for a leaf
+    children: Option<(usize, usize)>,
+    value: Option<T>,
+}
+
+/// A dynamic bounding volume hierarchy: a binary tree of boxes where every branch bounds its
+/// two children and values are stored in the leaves. Queries cost about the same whatever the
+/// sizes of the boxes, unlike a grid whose chunk size has to suit them.
+///
+/// Leaves store their box fattened by `margin`, so values that move a little stay where they
+/// are in the tree, and the tree is rebalanced with rotations as it changes so that it stays
+/// shallow.
+#[derive(Clone, Debug)]
+pub struct AabbTree<T> {
+    nodes: Vec<Node<T>>,
+    free: Vec<usize>,
+    root: Option<usize>,
+    len: usize,
+    margin: f32,
+}
+
+impl<T> Default for AabbTree<T> {
+    fn default() -> Self {
+        Self::new(0.)
+    }
+}
+
+impl<T> AabbTree<T> {
+    pub fn new(margin: f32) -> Self {
+        Self {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: None,
+            len: 0,
+            margin,
+        }
+    }
+
+    pub fn margin(&self) -> f32 {
+        self.margin
+    }
+
+    /// The number of levels below the root, which grows with the logarithm of `len`
+    pub fn height(&self) -> u32 {
+        self.root.map_or(0, |root| self.nodes[root].height)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, id: ProxyId) -> Option<&T> {
+        self.nodes.get(id.0).and_then(|node| node.value.as_ref())
+    }
+
+    /// The fattened box stored for a value, or `None` if it was removed
+    pub fn fat_aabb(&self, id: ProxyId) -> Option<Aabb> {
+        self.nodes
+            .get(id.0)
+            .filter(|node| node.value.is_some())
+            .map(|node| node.aabb)
+    }
+
+    pub fn insert(&mut self, aabb: Aabb, value: T) -> ProxyId {
+        let leaf = self.allocate(Node {
+            aabb: aabb.fattened(self.margin, Vec2::ZERO),
+            height: 0,
+            parent: None,
+            children: None,
+            value: Some(value),
+        });
+        self.insert_leaf(leaf);
+        self.len += 1;
+        ProxyId(leaf)
+    }
+
+    pub fn remove(&mut self, id: ProxyId) -> Option<T> {
+        let value = self.nodes.get_mut(id.0)?.value.take()?;
+        self.remove_leaf(id.0);
+        self.free.push(id.0);
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Moves a value to `aabb`, which is expected to keep moving by about `displacement`. The
+    /// value is only reinserted when `aabb` leaves its fattened box, or when that box has grown
+    /// far larger than it needs to be, and this returns whether it was.
+    ///
+    /// Panics if the value was removed.
+    pub fn move_proxy(&mut self, id: ProxyId, aabb: Aabb, displacement: Vec2) -> bool {
+        let Some(fat_aabb) = self.fat_aabb(id) else {
+            panic!("moved a removed proxy {id:?}");
+        };
+        let huge_aabb = aabb.fattened(4. * self.margin, displacement * 4.);
+        if fat_aabb.contains(&aabb) && huge_aabb.contains(&fat_aabb) {
+            return false;
+        }
+
+        self.remove_leaf(id.0);
+        self.nodes[id.0].aabb = aabb.fattened(self.margin, displacement);
+        self.insert_leaf(id.0);
+        true
+    }
+
+    /// Calls `f` on every value whose fattened box overlaps `aabb`
+    pub fn query<F: FnMut(ProxyId, &T)>(&self, aabb: Aabb, mut f: F) {
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.aabb.overlaps(&aabb) {
+                continue;
+            }
+            match (node.children, &node.value) {
+                (Some((left, right)), _) => stack.extend([left, right]),
+                (None, Some(value)) => f(ProxyId(index), value),
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn allocate(&mut self, node: Node<T>) -> usize {
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn insert_leaf(&mut self, leaf: usize) {
+        let Some(root) = self.root else {
+            self.root = Some(leaf);
+            return;
+        };
+
+        // Walk down while pairing the leaf with a child is cheaper than pairing it with the
+        // whole branch, where a box costs its perimeter and every enlarged ancestor adds its
+        // growth
+        let aabb = self.nodes[leaf].aabb;
+        let mut sibling = root;
+        while let Some((left, right)) = self.nodes[sibling].children {
+            let perimeter = self.nodes[sibling].aabb.perimeter();
+            let combined = self.nodes[sibling].aabb.union(&aabb).perimeter();
+            let cost = 2. * combined;
+            let inheritance = 2. * (combined - perimeter);
+            let child_cost = |index: usize| {
+                let node = &self.nodes[index];
+                let combined = node.aabb.union(&aabb).perimeter();
+                match node.children {
+                    Some(_) => combined - node.aabb.perimeter() + inheritance,
+                    None => combined + inheritance,
+                }
+            };
+            let (left_cost, right_cost) = (child_cost(left), child_cost(right));
+            if cost < left_cost && cost < right_cost {
+                break;
+            }
+            sibling = if left_cost < right_cost { left } else { right };
+        }
+
+        // Pair the leaf with the sibling under a new branch
+        let old_parent = self.nodes[sibling].parent;
+        let branch = self.allocate(Node {
+            aabb: self.nodes[sibling].aabb.union(&aabb),
+            height: 0,
+            parent: old_parent,
+            children: Some((sibling, leaf)),
+            value: None,
+        });
+        self.nodes[sibling].parent = Some(branch);
+        self.nodes[leaf].parent = Some(branch);
+        match old_parent {
+            Some(parent) => self.replace_child(parent, sibling, branch),
+            None => self.root = Some(branch),
+        }
+        self.refit(Some(branch));
+    }
+
+    fn remove_leaf(&mut self, leaf: usize) {
+        let Some(parent) = self.nodes[leaf].parent else {
+            self.root = None;
+            return;
+        };
+
+        // The sibling takes the place of the branch it shared with the leaf
+        let (left, right) = self.nodes[parent].children.expect("parent is a branch");
+        let sibling = if left == leaf { right } else { left };
+        let grandparent = self.nodes[parent].parent;
+        self.nodes[sibling].parent = grandparent;
+        match grandparent {
+            Some(grandparent) => self.replace_child(grandparent, parent, sibling),
+            None => self.root = Some(sibling),
+        }
+        self.nodes[parent].children = None;
+        self.free.push(parent);
+        self.nodes[leaf].parent = None;
+        self.refit(grandparent);
+    }
+
+    fn replace_child(&mut self, parent: usize, old: usize, new: usize) {
+        if let Some((left, right)) = self.nodes[parent].children.as_mut() {
+            if *left == old {
+                *left = new;
+            } else {
+                *right = new;
+            }
+        }
+    }
+
+    /// Rebalances and recomputes the boxes of `index` and every branch above it
+    fn refit(&mut self, mut index: Option<usize>) {
+        while let Some(i) = index {
+            let i = self.balance(i);
+            self.update(i);
+            index = self.nodes[i].parent;
+        }
+    }
+
+    /// Recomputes a branch's box and height from its children
+    fn update(&mut self, index: usize) {
+        let (left, right) = self.nodes[index]
+            .children
+            .expect("only branches are updated");
+        let (left, right) = (&self.nodes[left], &self.nodes[right]);
+        let aabb = left.aabb.union(&right.aabb);
+        let height = 1 + left.height.max(right.height);
+        let node = &mut self.nodes[index];
+        node.aabb = aabb;
+        node.height = height;
+    }
+
+    /// Rotates the taller child of `index` above it when one child is more than a level taller
+    /// than the other, returning the branch now in its place
+    fn balance(&mut self, index: usize) -> usize {
+        let Some((left, right)) = self.nodes[index].children else {
+            return index;
+        };
+        let (left_height, right_height) = (self.nodes[left].height, self.nodes[right].height);
+        if left_height > right_height + 1 {
+            self.rotate_up(index, left)
+        } else if right_height > left_height + 1 {
+            self.rotate_up(index, right)
+        } else {
+            index
+        }
+    }
+
+    /// Makes `child` the parent of `index`, keeping its taller child and handing its shorter
+    /// one to `index` in its own place
+    fn rotate_up(&mut self, index: usize, child: usize) -> usize {
+        let (first, second) = self.nodes[child]
+            .children
+            .expect("taller child is a branch");
+        let (keep, give) = if self.nodes[first].height > self.nodes[second].height {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        let parent = self.nodes[index].parent;
+        self.nodes[child].parent = parent;
+        match parent {
+            Some(parent) => self.replace_child(parent, index, child),
+            None => self.root = Some(child),
+        }
+        self.nodes[child].children = Some((index, keep));
+        self.nodes[index].parent = Some(child);
+
+        self.replace_child(index, child, give);
+        self.nodes[give].parent = Some(index);
+        self.update(index);
+        self.update(child);
+        child
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f32, y: f32) -> Aabb {
+        Aabb::new(Vec2::new(x, y), Vec2::new(x + 1., y + 1.))
+    }
+
+    /// Checks every branch bounds its children with the right height and that no branch is
+    /// unbalanced
+    fn validate<T>(tree: &AabbTree<T>) {
+        let mut stack: Vec<usize> = tree.root.into_iter().collect();
+        let mut leaves = 0;
+        while let Some(index) = stack.pop() {
+            let node = &tree.nodes[index];
+            let Some((left, right)) = node.children else {
+                assert_eq!(node.height, 0);
+                leaves += 1;
+                continue;
+            };
+            let (left_node, right_node) = (&tree.nodes[left], &tree.nodes[right]);
+            assert_eq!(left_node.parent, Some(index));
+            assert_eq!(right_node.parent, Some(index));
+            assert!(node.aabb.contains(&left_node.aabb) && node.aabb.contains(&right_node.aabb));
+            assert_eq!(node.height, 1 + left_node.height.max(right_node.height));
+            assert!(left_node.height.abs_diff(right_node.height) <= 1);
+            stack.extend([left, right]);
+        }
+        assert_eq!(leaves, tree.len());
+    }
+
+    fn query(tree: &AabbTree<usize>, aabb: Aabb) -> Vec<usize> {
+        let mut actual = Vec::new();
+        tree.query(aabb, |_, value| actual.push(*value));
+        actual.sort();
+        actual
+    }
+
+    #[test]
+    fn test_query() {
+        let mut tree = AabbTree::default();
+        for i in 0..10 {
+            tree.insert(square(i as f32 * 2., 0.), i);
+        }
+        assert_eq!(tree.len(), 10);
+        assert_eq!(
+            query(&tree, Aabb::new(Vec2::new(3.5, 0.5), Vec2::new(8., 0.5))),
+            vec![2, 3, 4]
+        );
+        assert_eq!(query(&tree, square(0., 5.)), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut tree = AabbTree::default();
+        let ids: Vec<_> = (0..5)
+            .map(|i| tree.insert(square(i as f32, 0.), i))
+            .collect();
+        assert_eq!(tree.remove(ids[2]), Some(2));
+        assert_eq!(tree.remove(ids[2]), None);
+        assert_eq!(
+            query(&tree, Aabb::new(Vec2::ZERO, Vec2::new(5., 1.))),
+            vec![0, 1, 3, 4]
+        );
+        for id in [ids[0], ids[1], ids[3], ids[4]] {
+            tree.remove(id);
+        }
+        assert!(tree.is_empty());
+        assert_eq!(query(&tree, square(0., 0.)), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_fattened_query() {
+        let mut tree = AabbTree::new(0.5);
+        let id = tree.insert(square(0., 0.), 0);
+        assert_eq!(
+            tree.fat_aabb(id),
+            Some(Aabb::new(Vec2::splat(-0.5), Vec2::splat(1.5)))
+        );
+        assert_eq!(query(&tree, square(1.25, 1.25)), vec![0]);
+        assert_eq!(query(&tree, square(1.75, 1.75)), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_move_proxy() {
+        let mut tree = AabbTree::new(0.5);
+        let id = tree.insert(square(0., 0.), 0);
+        let other = tree.insert(square(10., 0.), 1);
+
+        // Small moves stay inside the fattened box
+        assert!(!tree.move_proxy(id, square(0.25, 0.), Vec2::ZERO));
+        assert!(tree.move_proxy(id, square(1., 0.), Vec2::new(1., 0.)));
+        assert_eq!(
+            tree.fat_aabb(id),
+            Some(Aabb::new(Vec2::new(0.5, -0.5), Vec2::new(3.5, 1.5)))
+        );
+        assert!(!tree.move_proxy(id, square(2., 0.), Vec2::new(1., 0.)));
+
+        assert!(tree.move_proxy(id, square(9.5, 0.), Vec2::ZERO));
+        assert_eq!(
+            query(&tree, Aabb::new(Vec2::new(10., 0.), Vec2::new(10., 0.))),
+            vec![0, 1]
+        );
+        assert_eq!(tree.get(other), Some(&1));
+        validate(&tree);
+    }
+
+    #[test]
+    fn test_removed_proxy() {
+        let mut tree = AabbTree::new(0.5);
+        let id = tree.insert(square(0., 0.), 0);
+        let other = tree.insert(square(5., 0.), 1);
+        tree.remove(id);
+        assert_eq!(tree.fat_aabb(id), None);
+        assert!(tree.fat_aabb(other).is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "moved a removed proxy")]
+    fn test_move_removed_proxy() {
+        let mut tree = AabbTree::new(0.5);
+        let id = tree.insert(square(0., 0.), 0);
+        tree.insert(square(5., 0.), 1);
+        tree.remove(id);
+        tree.move_proxy(id, square(1., 0.), Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_balance() {
+        let mut tree = AabbTree::default();
+        let mut ids = Vec::new();
+        for i in 0..1024 {
+            ids.push(tree.insert(square(i as f32, 0.), i));
+            validate(&tree);
+        }
+        // A perfectly balanced tree of 1024 leaves has a height of 10
+        assert!(tree.height() <= 15, "height {}", tree.height());
+
+        for (value, id) in ids.into_iter().enumerate().step_by(2) {
+            assert_eq!(tree.remove(id), Some(value));
+        }
+        validate(&tree);
+        assert_eq!(tree.len(), 512);
+        assert_eq!(
+            query(
+                &tree,
+                Aabb::new(Vec2::new(99.5, 0.5), Vec2::new(101.5, 0.5))
+            ),
+            vec![99, 101]
+        );
+    }
+}
@@ -51,13 +51,87 @@ impl<T: std::fmt::Debug> ChunkMap<T> {
         }
     }
 
+    pub fn chunk_id(&self, position: Vec2) -> ChunkId {
+        ChunkId::from(position / self.chunk_size)
+    }
+
     pub fn insert(&mut self, position: Vec2, value: T) {
         self.map
-            .entry(ChunkId::from(position / self.chunk_size))
-            .or_insert_with(Vec::new)
+            .entry(self.chunk_id(position))
+            .or_default()
             .push(value);
     }
 
+    /// Every chunk the segment from `start` to `end` crosses
+    pub fn segment_chunk_ids(&self, start: Vec2, end: Vec2) -> Vec<ChunkId> {
+        let start = start / self.chunk_size;
+        let end = end / self.chunk_size;
+        let direction = end - start;
+        let mut id = ChunkId::from(start);
+        let last = ChunkId::from(end);
+
+        // Walk the grid one chunk boundary at a time, stepping along whichever axis is crossed
+        // first
+        let step = (direction.x.signum() as i32, direction.y.signum() as i32);
+        let t_delta = Vec2::ONE / direction.abs();
+        let first_boundary = Vec2::new(
+            if direction.x > 0. {
+                (id.x + 1) as f32
+            } else {
+                id.x as f32
+            },
+            if direction.y > 0. {
+                (id.y + 1) as f32
+            } else {
+                id.y as f32
+            },
+        );
+        let mut t_max = ((first_boundary - start) / direction).abs();
+        if direction.x == 0. {
+            t_max.x = f32::INFINITY;
+        }
+        if direction.y == 0. {
+            t_max.y = f32::INFINITY;
+        }
+
+        let steps = (last.x - id.x).abs() + (last.y - id.y).abs();
+        let mut ids = Vec::with_capacity(steps as usize + 1);
+        for _ in 0..steps {
+            ids.push(id);
+            if t_max.x < t_max.y {
+                id.x += step.0;
+                t_max.x += t_delta.x;
+            } else {
+                id.y += step.1;
+                t_max.y += t_delta.y;
+            }
+        }
+        ids.push(last);
+        ids
+    }
+
+    /// Removes one copy of the value from the chunk, dropping the chunk once it is empty
+    pub fn remove(&mut self, id: ChunkId, value: &T)
+    where
+        T: PartialEq,
+    {
+        let Some(values) = self.map.get_mut(&id) else {
+            return;
+        };
+        if let Some(index) = values.iter().position(|v| v == value) {
+            values.swap_remove(index);
+        }
+        if values.is_empty() {
+            self.map.remove(&id);
+        }
+    }
+
+    /// Every chunk the box spanning `min..max` covers
+    pub fn chunk_ids(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = ChunkId> {
+        let (min, max) = (self.chunk_id(min), self.chunk_id(max));
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| ChunkId { x, y }))
+    }
+
     pub fn pop(&mut self) -> Option<(ChunkId, T)> {
         let mut value = None;
         let mut empty_chunk_id = None;
@@ -81,6 +155,20 @@ impl<T: std::fmt::Debug> ChunkMap<T> {
         value
     }
 
+    /// Calls `f` on every item in the chunks the box spanning `min..max` covers. Items stored
+    /// in several of those chunks are visited once per chunk.
+    pub fn iter_bounds<F: FnMut(ChunkId, &T)>(&self, min: Vec2, max: Vec2, mut f: F) {
+        for chunk_id in self.chunk_ids(min, max) {
+            let Some(chunk) = self.map.get(&chunk_id) else {
+                continue;
+            };
+
+            for item in chunk.iter() {
+                f(chunk_id, item);
+            }
+        }
+    }
+
     pub fn iter_neighbors<F: FnMut(ChunkId, &T)>(&self, center: ChunkId, mut f: F) {
         for offset in OFFSETS {
             let chunk_id = ChunkId {
@@ -97,3 +185,66 @@ impl<T: std::fmt::Debug> ChunkMap<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: impl IntoIterator<Item = ChunkId>) -> Vec<(i32, i32)> {
+        let mut ids: Vec<_> = ids.into_iter().map(|id| (id.x, id.y)).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_segment_chunk_ids() {
+        let chunks = ChunkMap::<usize>::new(0, 10.);
+        let actual = chunks.segment_chunk_ids(Vec2::new(5., 5.), Vec2::new(35., 12.));
+        assert_eq!(ids(actual), vec![(0, 0), (1, 0), (2, 0), (2, 1), (3, 1)]);
+    }
+
+    #[test]
+    fn test_chunk_ids() {
+        let chunks = ChunkMap::<usize>::new(0, 10.);
+        let actual = chunks.chunk_ids(Vec2::new(-5., 5.), Vec2::new(15., 12.));
+        assert_eq!(
+            ids(actual),
+            vec![(-1, 0), (-1, 1), (0, 0), (0, 1), (1, 0), (1, 1)]
+        );
+    }
+
+    #[test]
+    fn test_iter_bounds() {
+        let mut chunks = ChunkMap::new(0, 10.);
+        chunks.insert(Vec2::new(5., 5.), 0);
+        chunks.insert(Vec2::new(45., 5.), 1);
+        for x in [-5., 5., 15.] {
+            chunks.insert(Vec2::new(x, 0.), 2);
+        }
+        let mut actual = Vec::new();
+        chunks.iter_bounds(Vec2::new(9., 9.), Vec2::new(50., 9.), |_, value| {
+            actual.push(*value)
+        });
+        actual.sort();
+        assert_eq!(actual, vec![0, 1, 2, 2]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut chunks = ChunkMap::new(0, 10.);
+        chunks.insert(Vec2::ZERO, 0);
+        chunks.insert(Vec2::new(15., 5.), 0);
+        chunks.insert(Vec2::new(5., 5.), 1);
+        chunks.remove(ChunkId { x: 0, y: 0 }, &0);
+        chunks.remove(ChunkId { x: 1, y: 0 }, &0);
+        assert_eq!(chunks.map.len(), 1);
+        assert_eq!(chunks.map[&ChunkId { x: 0, y: 0 }], vec![1]);
+    }
+
+    #[test]
+    fn test_point_segment_chunk_ids() {
+        let chunks = ChunkMap::<usize>::new(0, 10.);
+        let actual = chunks.segment_chunk_ids(Vec2::new(-5., 5.), Vec2::new(-5., 5.));
+        assert_eq!(ids(actual), vec![(-1, 0)]);
+    }
+}
@@ -1 +1,2 @@
+pub mod aabb_tree;
 pub mod chunk_map;